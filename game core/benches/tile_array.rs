@@ -0,0 +1,133 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::mem::size_of;
+use std::path::Path;
+use std::sync::Arc;
+use test::{black_box, Bencher};
+use war_economy_core::Definitions;
+use war_economy_core::map::tile::{TileArray, TileLocal, TileSizedData};
+use war_economy_core::map::tile::surface::TileSurface;
+use war_economy_core::map::units::TerrainHeight;
+
+const TILE_AMOUNT: u32 = 512 * 512;
+
+
+
+fn filler(definitions: &Definitions) -> TileLocal {
+    let surface = vec![TileSurface::new(0, 0); definitions.surface_types.layers.len()];
+
+    TileLocal::new(0, surface.into_boxed_slice())
+}
+
+fn tile_array() -> (TileArray, TileLocal) {
+    let definitions = Definitions::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(Definitions::DEFAULT_DIRECTORY))
+        .expect("default game set should load");
+    let filler = filler(&definitions);
+
+    (TileArray::new(Arc::new(definitions), filler.clone(), TILE_AMOUNT), filler)
+}
+
+
+
+/// Former layout of `TileArray`, every tile packed into bytes as its sized data followed by its surfaces.
+struct PackedTileArray {
+
+    tile_byte_size: usize,
+    byte_array: Box<[u8]>,
+
+}
+
+impl PackedTileArray {
+
+    fn new(filler: &TileLocal, tile_amount: u32) -> Self {
+        let tile_byte_size = size_of::<TileSizedData>() + size_of::<TileSurface>() * filler.surface.len();
+        let mut byte_array = vec![0; tile_amount as usize * tile_byte_size].into_boxed_slice();
+
+        for tile_bytes in byte_array.chunks_exact_mut(tile_byte_size) {
+            let pointer = tile_bytes.as_mut_ptr();
+
+            unsafe {
+                (pointer as *mut TileSizedData).write_unaligned(filler.main.clone());
+                for (layer, surface) in filler.surface.iter().enumerate() {
+                    (pointer.add(size_of::<TileSizedData>() + layer * size_of::<TileSurface>()) as *mut TileSurface)
+                        .write_unaligned(surface.clone());
+                }
+            }
+        }
+
+        Self {
+            tile_byte_size,
+            byte_array,
+        }
+    }
+
+    fn height(&self, index: usize) -> TerrainHeight {
+        let tile_bytes = &self.byte_array[index * self.tile_byte_size..(index + 1) * self.tile_byte_size];
+
+        unsafe { (tile_bytes.as_ptr() as *const TileSizedData).read_unaligned().height }
+    }
+
+    fn tile_amount(&self) -> usize {
+
+        self.byte_array.len() / self.tile_byte_size
+    }
+
+}
+
+
+
+#[bench]
+fn iterate_heights(bencher: &mut Bencher) {
+    let (tiles, _) = tile_array();
+
+    bencher.iter(|| {
+        tiles.iter().map(|tile| tile.main.height.to_meters() as i64).sum::<i64>()
+    });
+}
+
+#[bench]
+fn iterate_heights_main_slice(bencher: &mut Bencher) {
+    let (tiles, _) = tile_array();
+
+    bencher.iter(|| {
+        tiles.main_slice().iter().map(|main| main.height.to_meters() as i64).sum::<i64>()
+    });
+}
+
+#[bench]
+fn iterate_heights_packed(bencher: &mut Bencher) {
+    let (_, filler) = tile_array();
+    let tiles = PackedTileArray::new(&filler, TILE_AMOUNT);
+
+    bencher.iter(|| {
+        (0..tiles.tile_amount()).map(|tile_index| tiles.height(tile_index).to_meters() as i64).sum::<i64>()
+    });
+}
+
+#[bench]
+fn raise_heights_in_place(bencher: &mut Bencher) {
+    let (mut tiles, _) = tile_array();
+
+    bencher.iter(|| {
+        for tile in tiles.iter_mut() {
+            tile.main.height += TerrainHeight::from_meters(1);
+        }
+        black_box(&tiles);
+    });
+}
+
+#[bench]
+fn raise_heights_round_trip(bencher: &mut Bencher) {
+    let (mut tiles, _) = tile_array();
+
+    bencher.iter(|| {
+        for tile_index in 0..TILE_AMOUNT {
+            let mut tile = tiles.index(tile_index).to_local();
+            tile.main.height += TerrainHeight::from_meters(1);
+            let _ = tiles.put(tile_index, tile);
+        }
+        black_box(&tiles);
+    });
+}
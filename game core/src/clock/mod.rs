@@ -0,0 +1,189 @@
+use std::time::Duration;
+use crate::units::TimeSpan;
+
+
+
+/// Turns real elapsed time into fixed length simulation ticks.
+///
+/// Real time is accumulated in whole nanoseconds, and every tick simulates the same `tick_length`,
+/// so the same total of real time, at the same speed, always gives the same ticks,
+/// no matter how it was split between frames. Ticks above `max_ticks_per_advance` are deferred, not lost,
+/// so they only come out later, in following calls.
+pub struct GameClock {
+
+    pub tick_length: TimeSpan,
+    /// Real time, that a single tick takes at speed multiplier of 1.
+    pub real_tick_length: Duration,
+    /// Ticks above this are left for next calls of `GameClock::advance`, instead of stalling a frame.
+    pub max_ticks_per_advance: u32,
+
+    speed_levels: Vec<u32>,
+    speed_level: usize,
+    paused: bool,
+
+    accumulated_nanos: u128,
+
+}
+
+impl GameClock {
+
+    pub const DEFAULT_SPEED_LEVELS: [u32; 5] = [1, 2, 4, 8, 16];
+
+    pub fn new(tick_length: TimeSpan, real_tick_length: Duration, speed_levels: Vec<u32>) -> Self {
+
+        Self {
+            tick_length,
+            real_tick_length,
+            max_ticks_per_advance: 240,
+
+            speed_levels,
+            speed_level: 0,
+            paused: true,
+
+            accumulated_nanos: 0,
+        }
+    }
+
+    /// One hour per tick, with a day passing each second at the lowest speed.
+    pub fn new_default() -> Self {
+
+        Self::new(TimeSpan::HOUR, Duration::from_secs(1) / 24, Self::DEFAULT_SPEED_LEVELS.to_vec())
+    }
+
+
+
+    /// Returns amount of ticks, that should be simulated now, always 0 while paused.
+    /// Deferred ticks are kept for after the clock is resumed.
+    pub fn advance(&mut self, real_elapsed: Duration) -> u32 {
+        if self.paused {
+            return 0;
+        }
+
+        self.accumulated_nanos += real_elapsed.as_nanos() * self.speed_multiplier() as u128;
+
+        let real_tick_nanos = self.real_tick_length.as_nanos().max(1);
+        let ticks = (self.accumulated_nanos / real_tick_nanos).min(self.max_ticks_per_advance as u128);
+        self.accumulated_nanos -= ticks * real_tick_nanos;
+
+        ticks as u32
+    }
+
+
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+
+        self.paused
+    }
+
+
+
+    /// Levels beyond the last one are clamped to it.
+    pub fn set_speed_level(&mut self, speed_level: usize) {
+        self.speed_level = speed_level.min(self.speed_levels.len().saturating_sub(1));
+    }
+
+    pub fn speed_up(&mut self) {
+        self.set_speed_level(self.speed_level + 1);
+    }
+
+    pub fn slow_down(&mut self) {
+        self.set_speed_level(self.speed_level.saturating_sub(1));
+    }
+
+    pub fn speed_level(&self) -> usize {
+
+        self.speed_level
+    }
+
+    pub fn speed_multiplier(&self) -> u32 {
+
+        self.speed_levels.get(self.speed_level).copied().unwrap_or(1)
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn running_clock() -> GameClock {
+        let mut clock = GameClock::new(TimeSpan::HOUR, Duration::from_millis(10), vec![1, 2, 4]);
+        clock.resume();
+
+        clock
+    }
+
+    #[test]
+    fn ticks_dont_depend_on_frame_split() {
+        let mut whole = running_clock();
+        let mut split = running_clock();
+
+        let split_ticks: u32 = (0..7).map(|_| split.advance(Duration::from_millis(5))).sum();
+
+        assert_eq!(whole.advance(Duration::from_millis(35)), 3);
+        assert_eq!(split_ticks, 3);
+    }
+
+    #[test]
+    fn paused_clock_gives_no_ticks() {
+        let mut clock = GameClock::new(TimeSpan::HOUR, Duration::from_millis(10), vec![1]);
+
+        assert!(clock.is_paused());
+        assert_eq!(clock.advance(Duration::from_secs(1)), 0);
+
+        clock.resume();
+        assert_eq!(clock.advance(Duration::from_millis(10)), 1);
+    }
+
+    #[test]
+    fn deferred_ticks_wait_while_paused() {
+        let mut clock = running_clock();
+        clock.max_ticks_per_advance = 4;
+
+        assert_eq!(clock.advance(Duration::from_millis(100)), 4);
+
+        clock.pause();
+        assert_eq!(clock.advance(Duration::ZERO), 0);
+        assert_eq!(clock.advance(Duration::from_secs(1)), 0);
+
+        clock.resume();
+        assert_eq!(clock.advance(Duration::ZERO), 4);
+        assert_eq!(clock.advance(Duration::ZERO), 2);
+        assert_eq!(clock.advance(Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn speed_multiplies_ticks() {
+        let mut clock = running_clock();
+
+        clock.speed_up();
+        assert_eq!(clock.speed_multiplier(), 2);
+        assert_eq!(clock.advance(Duration::from_millis(20)), 4);
+
+        clock.set_speed_level(10);
+        assert_eq!(clock.speed_level(), 2);
+        assert_eq!(clock.advance(Duration::from_millis(20)), 8);
+
+        clock.slow_down();
+        clock.slow_down();
+        clock.slow_down();
+        assert_eq!(clock.speed_multiplier(), 1);
+        assert_eq!(clock.advance(Duration::from_millis(20)), 2);
+    }
+
+}
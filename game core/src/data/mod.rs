@@ -0,0 +1,283 @@
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use crate::error::{CoreError, CoreResult, ResultToCoreError};
+use crate::units::{GameDate, TimeSpan};
+
+pub mod pack;
+
+
+
+/// Parsed TOML data file, remembering its path for error reports.
+pub struct DataFile {
+
+    path: PathBuf,
+    table: Table,
+
+}
+
+impl DataFile {
+
+    pub fn load(path: PathBuf) -> CoreResult<Self> {
+        let text = read_to_string(&path).to_core_error()?;
+
+        Self::parse(path, &text)
+    }
+
+    /// Same as `load`, but a missing file is not an error.
+    pub fn load_optional(path: PathBuf) -> CoreResult<Option<Self>> {
+
+        if path.exists() {
+            Ok(Some(Self::load(path)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn parse(path: PathBuf, text: &str) -> CoreResult<Self> {
+
+        match text.parse::<Table>() {
+            Ok(table) => Ok(Self {
+                path,
+                table,
+            }),
+            Err(error) => Err(CoreError::DataParse { file: path, error }),
+        }
+    }
+
+
+
+    pub fn root(&self) -> DataEntry<'_> {
+
+        DataEntry {
+            file: &self.path,
+            key: String::new(),
+            value: None,
+            table: Some(&self.table),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+
+        &self.path
+    }
+
+}
+
+
+
+/// Single value inside of a `DataFile`, with the full key leading to it.
+#[derive(Clone)]
+pub struct DataEntry<'a> {
+
+    file: &'a Path,
+    key: String,
+    value: Option<&'a Value>,
+    /// Only set for the file root, which isn't a `Value`.
+    table: Option<&'a Table>,
+
+}
+
+impl<'a> DataEntry<'a> {
+
+    pub fn get(&self, key: &str) -> CoreResult<DataEntry<'a>> {
+
+        match self.get_optional(key)? {
+            Some(entry) => Ok(entry),
+            None => Err(CoreError::DataEntry {
+                file: self.file.to_path_buf(),
+                key: self.child_key(key),
+                problem: "missing".into(),
+            }),
+        }
+    }
+
+    pub fn get_optional(&self, key: &str) -> CoreResult<Option<DataEntry<'a>>> {
+        let table = self.as_table()?;
+
+        Ok(table.get(key).map(|value| DataEntry {
+            file: self.file,
+            key: self.child_key(key),
+            value: Some(value),
+            table: None,
+        }))
+    }
+
+
+
+    pub fn as_table(&self) -> CoreResult<&'a Table> {
+
+        match (self.table, self.value) {
+            (Some(table), _) => Ok(table),
+            (None, Some(Value::Table(table))) => Ok(table),
+            _ => Err(self.wrong_type("table")),
+        }
+    }
+
+    /// Entries of a table, in the order the keys are stored in.
+    pub fn table_entries(&self) -> CoreResult<Vec<(&'a str, DataEntry<'a>)>> {
+        let table = self.as_table()?;
+
+        Ok(table.iter().map(|(key, value)| (key.as_str(), DataEntry {
+            file: self.file,
+            key: self.child_key(key),
+            value: Some(value),
+            table: None,
+        })).collect())
+    }
+
+    pub fn array(&self) -> CoreResult<Vec<DataEntry<'a>>> {
+
+        match self.value {
+            Some(Value::Array(array)) => Ok(array.iter().enumerate().map(|(index, value)| DataEntry {
+                file: self.file,
+                key: format!("{}[{index}]", self.key),
+                value: Some(value),
+                table: None,
+            }).collect()),
+            _ => Err(self.wrong_type("array")),
+        }
+    }
+
+    /// Missing arrays are read as empty ones.
+    pub fn optional_array(&self, key: &str) -> CoreResult<Vec<DataEntry<'a>>> {
+
+        match self.get_optional(key)? {
+            Some(entry) => entry.array(),
+            None => Ok(vec![]),
+        }
+    }
+
+
+
+    pub fn as_str(&self) -> CoreResult<&'a str> {
+
+        match self.value {
+            Some(Value::String(string)) => Ok(string),
+            _ => Err(self.wrong_type("string")),
+        }
+    }
+
+    pub fn as_bool(&self) -> CoreResult<bool> {
+
+        match self.value {
+            Some(Value::Boolean(boolean)) => Ok(*boolean),
+            _ => Err(self.wrong_type("boolean")),
+        }
+    }
+
+    pub fn as_i64(&self) -> CoreResult<i64> {
+
+        match self.value {
+            Some(Value::Integer(integer)) => Ok(*integer),
+            _ => Err(self.wrong_type("integer")),
+        }
+    }
+
+    pub fn as_u64(&self) -> CoreResult<u64> {
+        let integer = self.as_i64()?;
+
+        u64::try_from(integer).map_err(|_| self.error("must not be negative"))
+    }
+
+    pub fn as_u32(&self) -> CoreResult<u32> {
+        let integer = self.as_i64()?;
+
+        u32::try_from(integer).map_err(|_| self.error(format!("must fit between 0 and {}", u32::MAX)))
+    }
+
+    pub fn as_i32(&self) -> CoreResult<i32> {
+        let integer = self.as_i64()?;
+
+        i32::try_from(integer).map_err(|_| self.error(format!("must fit between {} and {}", i32::MIN, i32::MAX)))
+    }
+
+    pub fn as_usize(&self) -> CoreResult<usize> {
+        let integer = self.as_i64()?;
+
+        usize::try_from(integer).map_err(|_| self.error("must not be negative"))
+    }
+
+    /// Integers are accepted too, so `2` doesn't have to be written as `2.0`.
+    pub fn as_f64(&self) -> CoreResult<f64> {
+
+        match self.value {
+            Some(Value::Float(float)) => Ok(*float),
+            Some(Value::Integer(integer)) => Ok(*integer as f64),
+            _ => Err(self.wrong_type("float")),
+        }
+    }
+
+    /// Reads table like `{ years = 1, days = 12 }`, summing every unit.
+    pub fn as_time_span(&self) -> CoreResult<TimeSpan> {
+        let mut time = TimeSpan::ZERO;
+
+        for (unit, amount_entry) in self.table_entries()? {
+            let amount = amount_entry.as_i64()?;
+
+            time += match unit {
+                "seconds" => TimeSpan::from_seconds(amount),
+                "minutes" => TimeSpan::from_minutes(amount),
+                "hours" => TimeSpan::from_hours(amount),
+                "days" => TimeSpan::from_days(amount),
+                "weeks" => TimeSpan::from_weeks(amount),
+                "months" => TimeSpan::from_months(amount),
+                "years" => TimeSpan::from_years(amount),
+                _ => return Err(amount_entry.error("unknown time unit")),
+            };
+        }
+
+        Ok(time)
+    }
+
+
+
+    /// Reads date string, like `"1918-11-11"`.
+    pub fn as_date(&self) -> CoreResult<GameDate> {
+        let text = self.as_str()?;
+
+        match text.parse::<GameDate>() {
+            Ok(date) => Ok(date),
+            Err(error) => Err(self.error(error.to_string())),
+        }
+    }
+
+
+
+    pub fn key(&self) -> &str {
+
+        &self.key
+    }
+
+    pub fn file(&self) -> &'a Path {
+
+        self.file
+    }
+
+    pub fn error(&self, problem: impl Into<String>) -> CoreError {
+
+        CoreError::DataEntry {
+            file: self.file.to_path_buf(),
+            key: self.key.clone(),
+            problem: problem.into(),
+        }
+    }
+
+
+
+    fn wrong_type(&self, expected: &str) -> CoreError {
+        let found = self.value.map(|value| value.type_str()).unwrap_or("table");
+
+        self.error(format!("expected {expected}, found {found}"))
+    }
+
+    fn child_key(&self, key: &str) -> String {
+
+        if self.key.is_empty() {
+            key.into()
+        } else {
+            format!("{}.{key}", self.key)
+        }
+    }
+
+}
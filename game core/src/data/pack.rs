@@ -0,0 +1,45 @@
+use std::path::{Path, PathBuf};
+use crate::data::DataFile;
+use crate::error::CoreResult;
+
+
+
+/// Description of a loaded game set directory, read from its `pack.toml`.
+/// Saves keep these to know, which packs they need.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PackManifest {
+
+    pub name: String,
+    pub version: String,
+    pub directory: PathBuf,
+
+}
+
+impl PackManifest {
+
+    pub const FILE_NAME: &'static str = "pack.toml";
+
+    pub fn load(directory: &Path) -> CoreResult<Self> {
+        let file = DataFile::load(directory.join(Self::FILE_NAME))?;
+        let root = file.root();
+
+        Ok(Self {
+            name: root.get("name")?.as_str()?.to_string(),
+            version: root.get("version")?.as_str()?.to_string(),
+            directory: directory.to_path_buf(),
+        })
+    }
+
+}
+
+
+
+/// Replaces definition with the same name, or appends it if it's a new one.
+/// Later packs override, or extend, what earlier ones defined.
+pub fn override_by_name<T>(definitions: &mut Vec<T>, new_definition: T, name: impl Fn(&T) -> &str) {
+
+    match definitions.iter().position(|definition| name(definition) == name(&new_definition)) {
+        Some(index) => definitions[index] = new_definition,
+        None => definitions.push(new_definition),
+    }
+}
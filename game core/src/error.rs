@@ -1,5 +1,9 @@
+use std::fmt::{Display as FmtDisplay, Formatter};
 use std::io::{Error as IOError, Result as IOResult};
-use crate::image::{ImageError, ImageResult};
+use std::path::PathBuf;
+use toml::de::Error as TomlError;
+use crate::image::{ImageDimensions, ImageError, ImageResult};
+use crate::units::GameDate;
 
 
 
@@ -53,4 +57,113 @@ pub enum CoreError {
 
     ImageError(ImageError),
 
+    DataParse { file: PathBuf, error: TomlError },
+    DataEntry { file: PathBuf, key: String, problem: String },
+
+    UnknownItemType { type_id: usize },
+    AmountTypeMismatch { item_type: String },
+    ZeroRewardFrequency { sector_type: String },
+
+    MissingVariantImage { path: PathBuf, problem: String },
+    TileDimensionsDontMatch { path: PathBuf, expected: ImageDimensions, found: ImageDimensions },
+    /// Layer beyond the defined ones, or a tile with fewer surfaces than there are layers.
+    UnknownSurfaceLayer { tile_index: usize, layer: usize },
+    EmptySurface { tile_index: usize, layer: usize },
+    UnknownSurfaceType { tile_index: usize, layer: usize, type_id: usize },
+    UnknownSurfaceVariant { tile_index: usize, layer: usize, type_name: String, variant: usize },
+    UnknownOwner { tile_index: usize, owner: usize },
+
+    MapImageDimensionsDontMatch { path: PathBuf, expected: ImageDimensions, found: ImageDimensions },
+    UnknownMapImageColor { path: PathBuf, x: u32, y: u32, color: String },
+    TileOutsideMap { x: u32, y: u32 },
+    TransferBeforeLastOne { date: GameDate, last_date: GameDate },
+
+    InvalidDate { text: String },
+
+    InvalidChunk { problem: String },
+
+    Validation(ValidationReport),
+
+}
+
+impl FmtDisplay for CoreError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+
+        match self {
+            CoreError::InvalidPath => formatter.write_str("invalid path"),
+            CoreError::FileError(error) => write!(formatter, "file error > {error}"),
+            CoreError::ImageError(error) => write!(formatter, "image error > {error:?}"),
+            CoreError::DataParse { file, error } => write!(formatter, "{} is not valid TOML > {error}", file.display()),
+            CoreError::DataEntry { file, key, problem } => write!(formatter, "{} > `{key}` {problem}", file.display()),
+            CoreError::UnknownItemType { type_id } => write!(formatter, "there's no item type with id {type_id}"),
+            CoreError::AmountTypeMismatch { item_type } => write!(formatter, "wrong amount type for item type `{item_type}`"),
+            CoreError::ZeroRewardFrequency { sector_type } => write!(formatter, "tile sector type `{sector_type}` has to have positive reward frequency"),
+            CoreError::MissingVariantImage { path, problem } => write!(formatter, "surface variant {} failed to load > {problem}", path.display()),
+            CoreError::TileDimensionsDontMatch { path, expected, found } =>
+                write!(formatter, "surface variant {} is {}x{}, but tiles are {}x{}", path.display(), found.x, found.y, expected.x, expected.y),
+            CoreError::UnknownSurfaceLayer { tile_index, layer } => write!(formatter, "surface layer {layer} of tile {tile_index} is either not defined, or missing from the tile"),
+            CoreError::EmptySurface { tile_index, layer } => write!(formatter, "tile {tile_index} has no surface in layer {layer}, which can't be empty"),
+            CoreError::UnknownSurfaceType { tile_index, layer, type_id } =>
+                write!(formatter, "tile {tile_index} has unknown surface type {type_id} in layer {layer}"),
+            CoreError::UnknownSurfaceVariant { tile_index, layer, type_name, variant } =>
+                write!(formatter, "tile {tile_index} has unknown variant {variant} of `{type_name}` in layer {layer}"),
+            CoreError::UnknownOwner { tile_index, owner } => write!(formatter, "tile {tile_index} is owned by unknown power {owner}"),
+            CoreError::MapImageDimensionsDontMatch { path, expected, found } =>
+                write!(formatter, "map image {} is {}x{}, but the map is {}x{} tiles", path.display(), found.x, found.y, expected.x, expected.y),
+            CoreError::UnknownMapImageColor { path, x, y, color } =>
+                write!(formatter, "map image {} has color {color} without a key entry, at tile ({x}, {y})", path.display()),
+            CoreError::TileOutsideMap { x, y } => write!(formatter, "tile ({x}, {y}) is outside of the map"),
+            CoreError::TransferBeforeLastOne { date, last_date } => write!(formatter, "tiles can't be transferred at {date}, before the last transfer at {last_date}"),
+            CoreError::InvalidDate { text } => write!(formatter, "`{text}` is not a valid date, like `1918-11-11`"),
+            CoreError::InvalidChunk { problem } => write!(formatter, "invalid map chunk > {problem}"),
+            CoreError::Validation(report) => report.fmt(formatter),
+        }
+    }
+}
+
+
+
+/// Every problem found by a validation pass, instead of just the first one.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+
+    pub problems: Vec<CoreError>,
+
+}
+
+impl ValidationReport {
+
+    pub fn new() -> Self {
+
+        Self {
+            problems: vec![],
+        }
+    }
+
+    pub fn push(&mut self, problem: CoreError) {
+        self.problems.push(problem);
+    }
+
+    pub fn into_result(self) -> CoreResult<()> {
+
+        if self.problems.is_empty() {
+            Ok(())
+        } else {
+            Err(CoreError::Validation(self))
+        }
+    }
+
+}
+
+impl FmtDisplay for ValidationReport {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+
+        write!(formatter, "validation found {} problems", self.problems.len())?;
+
+        for problem in &self.problems {
+            write!(formatter, "\n  - {problem}")?;
+        }
+
+        Ok(())
+    }
 }
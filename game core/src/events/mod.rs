@@ -0,0 +1,332 @@
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use crate::Game;
+use crate::units::{GameDate, TimeSpan};
+
+
+
+pub type EventId = u64;
+pub type EventCallback = Box<dyn FnMut(&mut Game, GameDate)>;
+
+/// Events due at the same date are dispatched in order, in which they were scheduled.
+#[derive(Default)]
+pub struct EventScheduler {
+
+    next_id: EventId,
+    events: BTreeMap<(GameDate, EventId), ScheduledEvent>,
+
+    dispatching: Option<EventId>,
+    dispatching_cancelled: bool,
+
+}
+
+impl EventScheduler {
+
+    pub fn new() -> Self {
+
+        Self::default()
+    }
+
+
+
+    pub fn schedule(&mut self, label: impl Into<String>, due: GameDate, recurrence: Recurrence, action: EventAction) -> EventId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.events.insert((due, id), ScheduledEvent {
+            id,
+            label: label.into(),
+            due,
+            first_due: due,
+            occurrence: 0,
+            recurrence,
+            action,
+        });
+
+        id
+    }
+
+    pub fn schedule_once(&mut self, label: impl Into<String>, due: GameDate, action: EventAction) -> EventId {
+
+        self.schedule(label, due, Recurrence::Once, action)
+    }
+
+    /// Returns `false`, if there's no such event pending.
+    pub fn cancel(&mut self, id: EventId) -> bool {
+
+        if self.dispatching == Some(id) {
+            self.dispatching_cancelled = true;
+
+            return true;
+        }
+
+        let key = self.events.iter().find(|(_, event)| event.id == id).map(|(key, _)| *key);
+
+        match key {
+            Some(key) => self.events.remove(&key).is_some(),
+            None => false,
+        }
+    }
+
+
+
+    /// Pending events, in order of dispatching.
+    pub fn pending(&self) -> impl Iterator<Item = &ScheduledEvent> {
+
+        self.events.values()
+    }
+
+    pub fn next_due(&self) -> Option<GameDate> {
+
+        self.events.keys().next().map(|(due, _)| *due)
+    }
+
+    pub fn len(&self) -> usize {
+
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+
+        self.events.is_empty()
+    }
+
+
+
+    /// Dispatches every event due until `current_date`, including events rescheduled,
+    /// or newly scheduled by the dispatched ones, if they are also due already.
+    pub fn dispatch_due(game: &mut Game, current_date: GameDate) {
+
+        while let Some(mut event) = game.scheduler.pop_due(current_date) {
+            game.scheduler.dispatching = Some(event.id);
+            game.scheduler.dispatching_cancelled = false;
+
+            match &mut event.action {
+                EventAction::Message(text) => game.messages.push(GameMessage {
+                    date: event.due,
+                    label: event.label.clone(),
+                    text: text.clone(),
+                }),
+                EventAction::Callback(callback) => callback(game, event.due),
+            }
+
+            game.scheduler.dispatching = None;
+
+            if !game.scheduler.dispatching_cancelled {
+                if let Some(next_due) = event.recurrence.due(event.first_due, event.occurrence + 1) {
+                    event.due = next_due;
+                    event.occurrence += 1;
+                    game.scheduler.events.insert((next_due, event.id), event);
+                }
+            }
+        }
+    }
+
+    fn pop_due(&mut self, current_date: GameDate) -> Option<ScheduledEvent> {
+        let key = *self.events.keys().next()?;
+
+        if key.0 > current_date {
+            return None;
+        }
+
+        self.events.remove(&key)
+    }
+
+}
+
+impl Debug for EventScheduler {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+
+        formatter.debug_list().entries(self.events.values()).finish()
+    }
+}
+
+
+
+#[derive(Debug)]
+pub struct ScheduledEvent {
+
+    pub id: EventId,
+    /// Only for debugging, and for messages.
+    pub label: String,
+    pub due: GameDate,
+    /// Recurring events count their dates from the first one, so they don't drift.
+    pub first_due: GameDate,
+    /// How many times the event was dispatched already.
+    pub occurrence: i64,
+    pub recurrence: Recurrence,
+    pub action: EventAction,
+
+}
+
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Recurrence {
+
+    Once,
+    /// Non positive spans are treated as `Recurrence::Once`.
+    Every(TimeSpan),
+    /// Calendar months, counted from the first date, so it keeps the day of month, only clamped in shorter months.
+    EveryMonths(i64),
+
+}
+
+impl Recurrence {
+
+    /// Date of the given occurrence, counted from 0 at `first_due`, `None` if there's no such occurrence.
+    pub fn due(&self, first_due: GameDate, occurrence: i64) -> Option<GameDate> {
+
+        if occurrence == 0 {
+            return Some(first_due);
+        }
+
+        match *self {
+            Recurrence::Once => None,
+            Recurrence::Every(span) => if span > TimeSpan::ZERO { Some(first_due + span * occurrence) } else { None },
+            Recurrence::EveryMonths(months) => if months > 0 { Some(first_due.add_months(months * occurrence)) } else { None },
+        }
+    }
+
+}
+
+
+
+pub enum EventAction {
+
+    /// Queued into `Game::messages`.
+    Message(String),
+    Callback(EventCallback),
+
+}
+
+impl Debug for EventAction {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+
+        match self {
+            EventAction::Message(text) => formatter.debug_tuple("Message").field(text).finish(),
+            EventAction::Callback(_) => formatter.write_str("Callback"),
+        }
+    }
+}
+
+
+
+#[derive(Clone, Debug)]
+pub struct GameMessage {
+
+    pub date: GameDate,
+    pub label: String,
+    pub text: String,
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+    use crate::scenario::Scenario;
+    use crate::units::calendar::CalendarDate;
+    use super::*;
+
+    fn test_game() -> Game {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+
+        Game::load(&[root.join(crate::Definitions::DEFAULT_DIRECTORY)], root.join(Scenario::DEFAULT_PATH)).unwrap()
+    }
+
+    fn date(year: i64, month: u8, day: u8) -> GameDate {
+
+        CalendarDate::new(year, month, day).unwrap().to_date()
+    }
+
+    fn message_labels(game: &Game) -> Vec<&str> {
+
+        game.messages.iter().map(|message| message.label.as_str()).collect()
+    }
+
+    #[test]
+    fn equal_dates_keep_scheduling_order() {
+        let mut game = test_game();
+        let due = game.world_time() + TimeSpan::HOUR;
+
+        for label in ["first", "second", "third"] {
+            game.scheduler.schedule_once(label, due, EventAction::Message(String::new()));
+        }
+        game.scheduler.schedule_once("earlier", due - TimeSpan::HOUR, EventAction::Message(String::new()));
+        assert_eq!(game.scheduler.pending().map(|event| event.label.as_str()).collect::<Vec<_>>(), vec!["earlier", "first", "second", "third"]);
+
+        EventScheduler::dispatch_due(&mut game, due);
+        assert_eq!(message_labels(&game), vec!["earlier", "first", "second", "third"]);
+        assert!(game.scheduler.is_empty());
+    }
+
+    #[test]
+    fn cancelled_events_are_not_dispatched() {
+        let mut game = test_game();
+        let due = game.world_time();
+
+        let cancelled = game.scheduler.schedule_once("cancelled", due, EventAction::Message(String::new()));
+        game.scheduler.schedule_once("kept", due, EventAction::Message(String::new()));
+        assert!(game.scheduler.cancel(cancelled));
+        assert!(!game.scheduler.cancel(cancelled));
+
+        // A recurring event cancelling itself isn't rescheduled.
+        game.scheduler.schedule("recurring", due, Recurrence::Every(TimeSpan::HOUR), EventAction::Callback(Box::new(|game, _| {
+            let id = game.scheduler.dispatching.unwrap();
+            assert!(game.scheduler.cancel(id));
+        })));
+
+        EventScheduler::dispatch_due(&mut game, due + TimeSpan::from_days(1));
+        assert_eq!(message_labels(&game), vec!["kept"]);
+        assert!(game.scheduler.is_empty());
+    }
+
+    #[test]
+    fn monthly_events_keep_their_day() {
+        let first_due = date(1919, 1, 31);
+        let recurrence = Recurrence::EveryMonths(1);
+        let dates: Vec<GameDate> = (0..5).map(|occurrence| recurrence.due(first_due, occurrence).unwrap()).collect();
+        assert_eq!(dates, vec![date(1919, 1, 31), date(1919, 2, 28), date(1919, 3, 31), date(1919, 4, 30), date(1919, 5, 31)]);
+
+        let mut game = test_game();
+        game.scheduler.schedule("monthly", first_due, recurrence, EventAction::Message(String::new()));
+        EventScheduler::dispatch_due(&mut game, date(1919, 5, 31));
+        assert_eq!(game.messages.iter().map(|message| message.date).collect::<Vec<_>>(), dates);
+        assert_eq!(game.scheduler.next_due(), Some(date(1919, 6, 30)));
+
+        assert_eq!(Recurrence::Once.due(first_due, 1), None);
+        assert_eq!(Recurrence::EveryMonths(0).due(first_due, 1), None);
+        assert_eq!(Recurrence::Every(TimeSpan::ZERO).due(first_due, 1), None);
+    }
+
+    #[test]
+    fn events_are_dispatched_across_ticks() {
+        let mut game = test_game();
+        let start = game.world_time();
+        let tick_length = game.clock.tick_length;
+
+        game.scheduler.schedule("recurring", start + tick_length * 2, Recurrence::Every(tick_length * 3), EventAction::Message(String::new()));
+        // Events scheduled by a dispatched one are dispatched in the same tick, if they are due already.
+        game.scheduler.schedule_once("chained", start + tick_length, EventAction::Callback(Box::new(|game, due| {
+            game.scheduler.schedule_once("follow up", due, EventAction::Message(String::new()));
+        })));
+
+        game.tick();
+        assert_eq!(message_labels(&game), vec!["follow up"]);
+
+        for _ in 0..7 {
+            game.tick();
+        }
+        assert_eq!(game.messages.iter().map(|message| (message.label.as_str(), message.date)).collect::<Vec<_>>(), vec![
+            ("follow up", start + tick_length),
+            ("recurring", start + tick_length * 2),
+            ("recurring", start + tick_length * 5),
+            ("recurring", start + tick_length * 8),
+        ]);
+        assert_eq!(game.scheduler.next_due(), Some(start + tick_length * 11));
+    }
+
+}
@@ -1,3 +1,5 @@
+use png::{BitDepth, ColorType};
+
 pub trait Overdraw<Another> {
     fn overdraw_on(&self, rhs: &mut Another);
 }
@@ -5,9 +7,14 @@ pub trait Overdraw<Another> {
 pub trait ColorFn {
 
     const BYTE_LENGTH: usize;
+    const PNG_COLOR_TYPE: ColorType;
+    const PNG_BIT_DEPTH: BitDepth;
 
     fn to_raw_bytes(self) -> [u8; Self::BYTE_LENGTH];
 
+    /// Grey on 16 bit scale, as stored in grayscale images.
+    fn from_grey(grey: u16) -> Self;
+
 }
 
 
@@ -31,10 +38,30 @@ impl Rgb8 {
         Self { r, g, b }
     }
 
+    /// Reads colors written like `#ff8000`.
+    pub fn from_hex(text: &str) -> Option<Self> {
+        let digits = text.strip_prefix('#')?;
+        // `from_str_radix` alone would take signs, like in `#+f+f+f`.
+        if digits.len() != 6 || !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let channel = |start: usize| u8::from_str_radix(&digits[start..start + 2], 16).ok();
+
+        Some(Self::new(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    pub fn to_hex(self) -> String {
+
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
 }
 
 impl ColorFn for Rgb8 {
     const BYTE_LENGTH: usize = 3;
+    const PNG_COLOR_TYPE: ColorType = ColorType::Rgb;
+    const PNG_BIT_DEPTH: BitDepth = BitDepth::Eight;
 
     fn to_raw_bytes(self) -> [u8; Self::BYTE_LENGTH] {
 
@@ -44,6 +71,12 @@ impl ColorFn for Rgb8 {
             self.b,
         ]
     }
+
+    fn from_grey(grey: u16) -> Self {
+        let grey = (grey >> 8) as u8;
+
+        Self::new(grey, grey, grey)
+    }
 }
 
 impl Overdraw<Rgb8> for Rgb8 {
@@ -107,6 +140,8 @@ impl Rgba8 {
 
 impl ColorFn for Rgba8 {
     const BYTE_LENGTH: usize = 4;
+    const PNG_COLOR_TYPE: ColorType = ColorType::Rgba;
+    const PNG_BIT_DEPTH: BitDepth = BitDepth::Eight;
 
     fn to_raw_bytes(self) -> [u8; Self::BYTE_LENGTH] {
 
@@ -117,6 +152,11 @@ impl ColorFn for Rgba8 {
             self.a,
         ]
     }
+
+    fn from_grey(grey: u16) -> Self {
+
+        Rgb8::from_grey(grey).into()
+    }
 }
 
 impl Overdraw<Rgb8> for Rgba8 {
@@ -148,6 +188,7 @@ impl From<Rgb8> for Rgba8 {
 
 
 
+#[derive(Clone, Copy, PartialEq)]
 pub struct Grey8 ( u8);
 
 impl Grey8 {
@@ -160,15 +201,39 @@ impl Grey8 {
         Self ( grey )
     }
 
+    pub const fn value(self) -> u8 {
+
+        self.0
+    }
+
 }
 
 impl ColorFn for Grey8 {
     const BYTE_LENGTH: usize = 1;
+    const PNG_COLOR_TYPE: ColorType = ColorType::Grayscale;
+    const PNG_BIT_DEPTH: BitDepth = BitDepth::Eight;
 
     fn to_raw_bytes(self) -> [u8; Self::BYTE_LENGTH] {
 
         [self.0]
     }
+
+    fn from_grey(grey: u16) -> Self {
+
+        Self ( (grey >> 8) as u8)
+    }
+}
+
+impl From<Rgb8> for Grey8 {
+    fn from(value: Rgb8) -> Self {
+        Self ( ((value.r as u16 + value.g as u16 + value.b as u16) / 3) as u8)
+    }
+}
+
+impl From<Rgba8> for Grey8 {
+    fn from(value: Rgba8) -> Self {
+        Rgb8::from(value).into()
+    }
 }
 
 impl Overdraw<Rgb8> for Grey8 {
@@ -197,3 +262,85 @@ impl Overdraw<Grey8> for Grey8 {
         rhs.0 = self.0;
     }
 }
+
+
+
+/// Grey with full precision of 16 bit images, like heightmaps.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Grey16 ( u16);
+
+impl Grey16 {
+
+    pub const WHITE: Self = Self::new(u16::MAX);
+    pub const BLACK: Self = Self::new(0);
+
+    pub const fn new(grey: u16) -> Self {
+
+        Self ( grey )
+    }
+
+    pub const fn value(self) -> u16 {
+
+        self.0
+    }
+
+}
+
+impl ColorFn for Grey16 {
+    const BYTE_LENGTH: usize = 2;
+    const PNG_COLOR_TYPE: ColorType = ColorType::Grayscale;
+    const PNG_BIT_DEPTH: BitDepth = BitDepth::Sixteen;
+
+    fn to_raw_bytes(self) -> [u8; Self::BYTE_LENGTH] {
+
+        self.0.to_be_bytes()
+    }
+
+    fn from_grey(grey: u16) -> Self {
+
+        Self ( grey)
+    }
+}
+
+impl From<Rgb8> for Grey16 {
+    fn from(value: Rgb8) -> Self {
+        Self ( Grey8::from(value).0 as u16 * (u16::MAX / u8::MAX as u16))
+    }
+}
+
+impl From<Rgba8> for Grey16 {
+    fn from(value: Rgba8) -> Self {
+        Rgb8::from(value).into()
+    }
+}
+
+impl Overdraw<Grey16> for Grey16 {
+    fn overdraw_on(&self, rhs: &mut Grey16) {
+        rhs.0 = self.0;
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn hex_round_trip() {
+        let color = Rgb8::new(255, 128, 0);
+
+        assert!(Rgb8::from_hex("#ff8000") == Some(color));
+        assert!(Rgb8::from_hex("#FF8000") == Some(color));
+        assert!(Rgb8::from_hex(&color.to_hex()) == Some(color));
+    }
+
+    #[test]
+    fn invalid_hex_is_rejected() {
+        for text in ["#+f+f+f", "#-1-1-1", "ff8000", "#ff800", "#ff80000", "#ff 800", "#gg8000", "#ff80é", ""] {
+            assert!(Rgb8::from_hex(text).is_none(), "`{text}` was accepted");
+        }
+    }
+
+}
@@ -1,5 +1,5 @@
 use png::{BitDepth, ColorType};
-use crate::image::color::{Rgb8, Rgba8};
+use crate::image::color::{ColorFn, Rgb8, Rgba8};
 
 
 pub fn pass_bit_depth(raw_image_buffer: &mut [u8], bit_depth: BitDepth) -> Vec<u16> {
@@ -52,18 +52,18 @@ pub fn pass_bit_depth(raw_image_buffer: &mut [u8], bit_depth: BitDepth) -> Vec<u
             values.reserve(raw_image_buffer.len());
 
             for raw_image_byte in raw_image_buffer {
-                values.push(*raw_image_byte as u16 * u8::MAX as u16);
+                values.push(*raw_image_byte as u16 * (u16::MAX / u8::MAX as u16));
             }
 
             values
         },
         BitDepth::Sixteen => {
-            let mut values = vec![0; raw_image_buffer.len()];
-            values.reserve(raw_image_buffer.len() / 2);
+            let mut values = vec![0; raw_image_buffer.len() / 2];
 
+            // PNG stores 16 bit values in big endian.
             for (value_index, value) in values.iter_mut().enumerate() {
                 let raw_data_index = value_index * 2;
-                *value = raw_image_buffer[raw_data_index] as u16 | ((raw_image_buffer[raw_data_index + 1] as u16) << 8);
+                *value = ((raw_image_buffer[raw_data_index] as u16) << 8) | raw_image_buffer[raw_data_index + 1] as u16;
             }
 
             values
@@ -73,9 +73,33 @@ pub fn pass_bit_depth(raw_image_buffer: &mut [u8], bit_depth: BitDepth) -> Vec<u
 
 
 
-pub fn pass_color_type<Color: From<Rgb8> + From<Rgba8>>(image_value_buffer: Vec<u16>, color_type: ColorType) -> Vec<Color> {
+pub fn pass_color_type<Color: ColorFn + From<Rgb8> + From<Rgba8>>(image_value_buffer: Vec<u16>, color_type: ColorType) -> Vec<Color> {
 
     match color_type {
+        ColorType::Grayscale => {
+
+            image_value_buffer.into_iter().map(Color::from_grey).collect()
+        },
+        ColorType::GrayscaleAlpha => {
+            let pixel_amount = image_value_buffer.len() / 2;
+            let mut pixels = Vec::with_capacity(pixel_amount);
+
+            for pixel_index in 0..pixel_amount {
+                let value_index = pixel_index * 2;
+                let grey = (image_value_buffer[value_index] >> 8) as u8;
+
+                pixels.push(
+                    Rgba8::new(
+                        grey,
+                        grey,
+                        grey,
+                        (image_value_buffer[value_index + 1] >> 8) as u8,
+                    ).into()
+                );
+            }
+
+            pixels
+        },
         ColorType::Rgb => {
             let pixel_amount = image_value_buffer.len() / 3;
             let mut pixels = vec![];
@@ -115,6 +139,6 @@ pub fn pass_color_type<Color: From<Rgb8> + From<Rgba8>>(image_value_buffer: Vec<
 
             pixels
         },
-        _ => todo!("Some colors decompleted."),
+        ColorType::Indexed => unreachable!("indexed images are expanded to RGB by the decoder"),
     }
 }
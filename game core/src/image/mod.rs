@@ -1,7 +1,7 @@
 use std::cmp::Ordering;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::ops::{Add, Div, Mul, Sub};
-use png::{Decoder, DecodingError, OutputInfo};
+use png::{Decoder, DecodingError, Encoder, EncodingError, OutputInfo, Transformations};
 use crate::image::color::{ColorFn, Overdraw, Rgb8, Rgba8};
 
 pub mod color;
@@ -9,6 +9,7 @@ pub mod file_load;
 
 
 
+#[derive(Clone)]
 pub struct Image<Color: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8>> {
 
     pixels: Vec<Color>,
@@ -37,8 +38,10 @@ impl<Color: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8>> Image
         }
     }
 
+    /// Indexed images are expanded to RGB, or RGBA if the palette has transparency.
     pub fn load_png<R: Read>(reader: R) -> ImageResult<Self> {
-        let decoder = Decoder::new(reader);
+        let mut decoder = Decoder::new(reader);
+        decoder.set_transformations(Transformations::EXPAND);
         let mut png_reader = decoder.read_info().to_image_result()?;
 
         let mut pixel_buffer = vec![0; png_reader.output_buffer_size()];
@@ -54,6 +57,20 @@ impl<Color: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8>> Image
 
 
 
+    pub fn save_png<W: Write>(&self, writer: W) -> ImageResult<()>
+        where [(); Color::BYTE_LENGTH]: Sized
+    {
+        let mut encoder = Encoder::new(writer, self.dimensions.x as u32, self.dimensions.y as u32);
+        encoder.set_color(Color::PNG_COLOR_TYPE);
+        encoder.set_depth(Color::PNG_BIT_DEPTH);
+
+        let mut png_writer = encoder.write_header().to_image_result()?;
+
+        png_writer.write_image_data(&self.raw_u8_bytes()).to_image_result()
+    }
+
+
+
     pub fn overdraw_image<
         Filler: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8> + Overdraw<Color>,
     >(&mut self, filler: &Image<Filler>, draw_offset: ImageDimensions) -> ImageResult<()> {
@@ -99,6 +116,33 @@ impl<Color: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8>> Image
         Ok(())
     }
 
+    /// Stretches the filler over `draw_size` pixels, picking the nearest filler pixel for each one.
+    pub fn overdraw_image_scaled<
+        Filler: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8> + Overdraw<Color>,
+    >(&mut self, filler: &Image<Filler>, draw_offset: ImageDimensions, draw_size: ImageDimensions) -> ImageResult<()> {
+        if draw_offset + draw_size > self.dimensions || filler.dimensions.len() == 0 {
+            return Err(ImageError::DimensionsDontMatch);
+        }
+
+        for draw_position in draw_size {
+            let filler_position = draw_position * filler.dimensions / draw_size;
+            let self_position = draw_position + draw_offset;
+
+            filler.pixels[filler_position.index_on_bigger_image(filler.dimensions.x)]
+                .overdraw_on(&mut self.pixels[self_position.index_on_bigger_image(self.dimensions.x)]);
+        }
+
+        Ok(())
+    }
+
+    /// Parts outside of the image are skipped.
+    pub fn fill_rectangle(&mut self, color: Color, offset: ImageDimensions, size: ImageDimensions) {
+
+        for position in size {
+            self.set_pixel(position + offset, color);
+        }
+    }
+
     /// Horribly ineffective operation.
     pub fn overdraw_image_rescaled<
         Filler: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8> + Overdraw<Color>,
@@ -153,6 +197,20 @@ impl<Color: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8>> Image
 
     }
 
+    /// Swaps rows with columns, mirroring the image along its diagonal.
+    pub fn transpose(&mut self) {
+        let image_copy = self.pixels.clone();
+        let ImageDimensions { x: width, y: height } = self.dimensions;
+
+        for y in 0..height {
+            for x in 0..width {
+                self.pixels[x * height + y] = image_copy[y * width + x];
+            }
+        }
+
+        self.dimensions = ImageDimensions::new(height, width);
+    }
+
 
 
     pub fn raw_u8_bytes(&self) -> Vec<u8>
@@ -170,6 +228,23 @@ impl<Color: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8>> Image
         data
     }
 
+    pub fn pixel(&self, position: ImageDimensions) -> Option<Color> {
+
+        if position.x < self.dimensions.x && position.y < self.dimensions.y {
+            Some(self.pixels[position.index_on_bigger_image(self.dimensions.x)])
+        } else {
+            None
+        }
+    }
+
+    /// Positions outside of the image are ignored.
+    pub fn set_pixel(&mut self, position: ImageDimensions, color: Color) {
+
+        if position.x < self.dimensions.x && position.y < self.dimensions.y {
+            self.pixels[position.index_on_bigger_image(self.dimensions.x)] = color;
+        }
+    }
+
     pub fn dimensions(&self) -> ImageDimensions {
 
         self.dimensions
@@ -355,6 +430,15 @@ pub trait ToImageResult<T> {
     fn to_image_result(self) -> ImageResult<T>;
 }
 
+impl<T> ToImageResult<T> for Result<T, EncodingError> {
+    fn to_image_result(self) -> ImageResult<T> {
+        match self {
+            Ok(t) => Ok(t),
+            Err(error) => Err(ImageError::PngSave(error)),
+        }
+    }
+}
+
 impl<T> ToImageResult<T> for Result<T, DecodingError> {
     fn to_image_result(self) -> ImageResult<T> {
         match self {
@@ -372,6 +456,7 @@ pub type ImageResult<T> = Result<T, ImageError>;
 pub enum ImageError {
 
     PngLoad(DecodingError),
+    PngSave(EncodingError),
     DimensionsDontMatch,
 
 }
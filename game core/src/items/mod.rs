@@ -2,13 +2,15 @@ pub mod units;
 
 use std::sync::Arc;
 use crate::Definitions;
-use crate::items::units::{DeterminedAmount, AmountType, CaloriesRate, RawAmount};
+use crate::data::DataEntry;
+use crate::error::{CoreError, CoreResult, OptionToCoreError};
+use crate::items::units::{DeterminedAmount, AmountType, Calories, CaloriesRate, RawAmount, Weight};
 
 
 
 pub struct ItemType {
 
-    pub name: &'static str,
+    pub name: String,
     pub amount_type: AmountType,
     pub production_per_person: RawAmount,
     pub max_production_per_tile: RawAmount,
@@ -19,7 +21,7 @@ pub struct ItemType {
 impl ItemType {
 
     pub fn new(
-        name: &'static str,
+        name: String,
         amount_type: AmountType,
         production_per_person: RawAmount,
         max_production_per_tile: RawAmount,
@@ -35,6 +37,40 @@ impl ItemType {
         }
     }
 
+    /// Weighed amounts are read in tons, counted ones in pieces.
+    pub fn from_data(entry: &DataEntry) -> CoreResult<Self> {
+        let name = entry.get("name")?.as_str()?.to_string();
+        let amount_type_entry = entry.get("amount_type")?;
+        let amount_type = match amount_type_entry.as_str()? {
+            "weight" => AmountType::Weight,
+            "count" => AmountType::Count,
+            _ => return Err(amount_type_entry.error("must be either \"weight\", or \"count\"")),
+        };
+        let read_amount = |key: &str| -> CoreResult<RawAmount> {
+            let amount = entry.get(key)?.as_u64()?;
+
+            Ok(match amount_type {
+                AmountType::Weight => Weight::from_tons(amount).as_raw_amount(),
+                AmountType::Count => DeterminedAmount::new_counted(amount).get_raw_amount(),
+            })
+        };
+
+        let type_values = match entry.get_optional("food")? {
+            Some(food) => ItemTypeValues::Food {
+                calories_rate: CaloriesRate::from_x_per_kilo_grams(Calories::from_kcal(food.get("kcal_per_kilogram")?.as_u64()?)),
+            },
+            None => ItemTypeValues::None,
+        };
+
+        Ok(Self::new(
+            name,
+            amount_type,
+            read_amount("production_per_person")?,
+            read_amount("max_production_per_tile")?,
+            type_values,
+        ))
+    }
+
 }
 
 
@@ -59,20 +95,15 @@ pub struct Item {
 
 impl Item {
 
-    pub fn new(definitions: Arc<Definitions>, type_id: usize, amount: DeterminedAmount) -> Self {
-
-        Self::new_checked(definitions, type_id, amount).expect("Item creation error. Wrong amount type.")
-    }
-
-    pub fn new_checked(definitions: Arc<Definitions>, type_id: usize, amount: DeterminedAmount) -> Option<Self> {
-        let item_type = &definitions.item_types[type_id];
+    pub fn new(definitions: Arc<Definitions>, type_id: usize, amount: DeterminedAmount) -> CoreResult<Self> {
+        let item_type = definitions.item_types.get(type_id).to_core_error(CoreError::UnknownItemType { type_id })?;
 
         if item_type.amount_type != amount.get_amount_type() {
 
-            return None;
+            return Err(CoreError::AmountTypeMismatch { item_type: item_type.name.clone() });
         }
 
-        Some ( Self {
+        Ok( Self {
             type_id,
             amount: amount.get_raw_amount(),
         })
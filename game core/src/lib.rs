@@ -1,22 +1,33 @@
 #![feature(generic_const_exprs)]
 
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use crate::image::ImageDimensions;
-use crate::items::{ItemType, ItemTypeValues};
-use crate::items::units::{AmountType, Calories, CaloriesRate, Weight};
-use crate::map::{Map, MapSettings, MapShape};
+use std::time::Duration;
+use crate::clock::GameClock;
+use crate::data::DataFile;
+use crate::data::pack::{override_by_name, PackManifest};
+use crate::error::{CoreError, CoreResult, ValidationReport};
+use crate::events::{EventScheduler, GameMessage};
+use crate::items::ItemType;
+use crate::map::Map;
+use crate::map::climate::Biome;
+use crate::map::ownership::TransferReason;
 use crate::map::tile::sectors::TileSectorType;
 use crate::map::tile::surface::{SurfaceTypes};
-use crate::map::units::TerrainHeight;
 use crate::powers::Power;
-use crate::units::Time;
+use crate::scenario::Scenario;
+use crate::units::{GameDate, TimeSpan};
 
+pub mod clock;
+pub mod data;
 pub mod image;
 pub mod items;
 pub mod map;
 pub mod population;
 pub mod powers;
+pub mod scenario;
 pub mod error;
+pub mod events;
 pub mod units;
 
 
@@ -26,7 +37,11 @@ pub struct Game {
     pub definitions: Arc<Definitions>,
     pub powers: Vec<Power>,
 
-    world_time: Time,
+    world_time: GameDate,
+    pub clock: GameClock,
+    pub scheduler: EventScheduler,
+    /// Messages of dispatched events, waiting to be read.
+    pub messages: Vec<GameMessage>,
 
     pub map: Map,
 
@@ -34,31 +49,83 @@ pub struct Game {
 
 impl Game {
 
-    pub fn new() -> Self {
-        let definitions = Arc::new(Definitions::new_default());
-        let world_time = Time::from_years(1918);
+    /// Default definitions, with the default scenario.
+    pub fn new() -> CoreResult<Self> {
+
+        Self::load(&[PathBuf::from(Definitions::DEFAULT_DIRECTORY)], Scenario::DEFAULT_PATH.into())
+    }
+
+    pub fn load(pack_directories: &[PathBuf], scenario_path: PathBuf) -> CoreResult<Self> {
+        let definitions = Arc::new(Definitions::load_packs(pack_directories)?);
+        let scenario = Scenario::load(scenario_path, &definitions)?;
+        let game = Self::from_scenario(definitions, scenario);
+        game.definitions.validate_map(&game.map, game.powers.len())?;
+
+        Ok(game)
+    }
+
+    pub fn from_scenario(definitions: Arc<Definitions>, scenario: Scenario) -> Self {
 
         Self {
-            definitions: definitions.clone(),
-            powers: vec![
-                Power::new("no state".to_string()),
-                Power::new("Second Polish Republic".to_string()),
-                Power::new("Arab Republic of Egypt".to_string()),
-                Power::new("Kingdom of Hungary".to_string()),
-            ],
-
-            world_time,
-
-            map: Map::new(
-                definitions.clone(),
-                MapSettings::new(
-                    TerrainHeight::from_meters(1_000),
-                    MapShape::Rectangular { width: 30, height: 30}),
-                world_time
-            ),
+            map: Map::from_scenario(definitions.clone(), &scenario),
+            definitions,
+            powers: scenario.powers,
+
+            world_time: scenario.start_date,
+            clock: GameClock::new_default(),
+            scheduler: EventScheduler::new(),
+            messages: vec![],
         }
     }
 
+
+
+    /// Simulates as many ticks, as the clock gives for `real_elapsed` time. Returns their amount.
+    pub fn advance(&mut self, real_elapsed: Duration) -> u32 {
+        let ticks = self.clock.advance(real_elapsed);
+
+        for _ in 0..ticks {
+            self.tick();
+        }
+
+        ticks
+    }
+
+    /// Single fixed length step, independent of real time.
+    pub fn tick(&mut self) {
+        let tick_length = self.clock.tick_length;
+
+        self.world_time += tick_length;
+        self.map.tick(self.world_time, tick_length);
+
+        EventScheduler::dispatch_due(self, self.world_time);
+    }
+
+
+
+    /// See `Map::export_images`.
+    pub fn export_map_images(&self, directory: &Path) -> CoreResult<()> {
+
+        self.map.export_images(directory, &self.powers)
+    }
+
+    /// See `Map::transfer_province`, dated with the current world time.
+    pub fn transfer_province(&mut self, province: usize, new_owner: usize, reason: TransferReason) -> CoreResult<usize> {
+
+        self.map.transfer_province(province, new_owner, reason, self.world_time, self.powers.len())
+    }
+
+    /// See `Map::transfer_region`, dated with the current world time.
+    pub fn transfer_region(&mut self, region: usize, new_owner: usize, reason: TransferReason) -> CoreResult<usize> {
+
+        self.map.transfer_region(region, new_owner, reason, self.world_time, self.powers.len())
+    }
+
+    pub fn world_time(&self) -> GameDate {
+
+        self.world_time
+    }
+
 }
 
 
@@ -68,55 +135,110 @@ pub struct Definitions {
     pub item_types: Vec<ItemType>,
     pub tile_sector_types: Vec<TileSectorType>,
     pub surface_types: SurfaceTypes,
+    /// Checked in order by the map generator, the first one matching a tile's climate sets its surface.
+    pub biomes: Vec<Biome>,
+
+    packs: Vec<PackManifest>,
 
 }
 
 impl Definitions {
 
-    pub fn new_default() -> Self {
+    pub const DEFAULT_DIRECTORY: &'static str = "game sets/historical";
 
-        Self {
+    pub fn load(directory: &Path) -> CoreResult<Self> {
 
+        Self::load_packs(&[directory.to_path_buf()])
+    }
 
+    /// Packs are stacked in the given order, so later ones override, or extend earlier ones.
+    pub fn load_packs(directories: &[PathBuf]) -> CoreResult<Self> {
+        let mut definitions = Self {
+            item_types: vec![],
+            tile_sector_types: vec![],
+            surface_types: SurfaceTypes::new_empty(),
+            biomes: vec![],
 
-            item_types: vec![
-                ItemType::new(
-                    "maize",
-                    AmountType::Weight,
-                    Weight::from_tons(20).as_raw_amount(),
-                    Weight::from_tons(3500).as_raw_amount(),
-                    ItemTypeValues::Food {
-                        calories_rate: CaloriesRate::from_x_per_kilo_grams(Calories::from_kcal(3650)),
-                    },
-                ),
-            ],
+            packs: vec![],
+        };
 
+        for directory in directories {
+            definitions.load_pack(directory)?;
+        }
+        definitions.validate()?;
 
+        Ok(definitions)
+    }
 
-            tile_sector_types: vec![
-                TileSectorType::new(
-                    "farm",
-                    Time::YEAR,
-                    Time::from_days(280),
-                ),
-            ],
+    /// Reads `pack.toml`, and optional `items.toml`, `sectors.toml`, `surface.toml` and `biomes.toml` from the pack `directory`.
+    fn load_pack(&mut self, directory: &Path) -> CoreResult<()> {
+        let manifest = PackManifest::load(directory)?;
 
+        if let Some(items_file) = DataFile::load_optional(directory.join("items.toml"))? {
+            for item_entry in items_file.root().optional_array("item")? {
+                override_by_name(&mut self.item_types, ItemType::from_data(&item_entry)?, |item_type| &item_type.name);
+            }
+        }
 
+        if let Some(sectors_file) = DataFile::load_optional(directory.join("sectors.toml"))? {
+            for sector_entry in sectors_file.root().optional_array("sector")? {
+                override_by_name(&mut self.tile_sector_types, TileSectorType::from_data(&sector_entry)?, |sector_type| &sector_type.name);
+            }
+        }
+
+        if let Some(surface_file) = DataFile::load_optional(directory.join("surface.toml"))? {
+            self.surface_types.merge_data(directory, &surface_file.root())?;
+        }
 
-            surface_types: SurfaceTypes::new(
-                vec![
-                    vec![
-                        ("game sets/historical/surface/plains".into(), 4),
-                        ("game sets/historical/surface/dessert".into(), 1),
-                        ("game sets/historical/surface/mountains".into(), 3),
-                    ]
-                ],
-                ImageDimensions::new(64, 64),
-            ).unwrap(),
+        if let Some(biomes_file) = DataFile::load_optional(directory.join("biomes.toml"))? {
+            for biome_entry in biomes_file.root().optional_array("biome")? {
+                override_by_name(&mut self.biomes, Biome::from_data(&biome_entry, &self.surface_types)?, |biome| &biome.name);
+            }
+        }
+
+        self.packs.push(manifest);
+
+        Ok(())
+    }
 
 
 
+    /// Cross checks definitions, collecting every problem found.
+    pub fn validate(&self) -> CoreResult<()> {
+        let mut report = ValidationReport::new();
+
+        for sector_type in &self.tile_sector_types {
+            if sector_type.reward_frequency <= TimeSpan::ZERO {
+                report.push(CoreError::ZeroRewardFrequency { sector_type: sector_type.name.clone() });
+            }
+        }
+
+        self.surface_types.validate(&mut report);
+
+        report.into_result()
+    }
+
+    /// Cross checks every tile of the `map` against definitions, and the number of powers.
+    pub fn validate_map(&self, map: &Map, power_amount: usize) -> CoreResult<()> {
+        let mut report = ValidationReport::new();
+        let (_, tiles) = map.get_terrain();
+
+        for (tile_index, tile) in tiles.into_iter().enumerate() {
+            if tile.main.owner >= power_amount {
+                report.push(CoreError::UnknownOwner { tile_index, owner: tile.main.owner });
+            }
         }
+
+        self.surface_types.validate_tiles(tiles, map.properties.seed, &mut report);
+
+        report.into_result()
+    }
+
+
+
+    pub fn packs(&self) -> &[PackManifest] {
+
+        &self.packs
     }
 
 }
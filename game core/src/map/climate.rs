@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use crate::data::DataEntry;
+use crate::error::CoreResult;
+use crate::map::MapSettings;
+use crate::map::coordinates::TileCoordinates;
+use crate::map::generator::fractal_noise;
+use crate::map::tile::TileLocal;
+use crate::map::tile::surface::SurfaceTypes;
+
+
+
+/// Yearly averages of a tile.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct TileClimate {
+
+    /// In degrees Celsius.
+    pub temperature: f32,
+    /// In millimeters of rain per year.
+    pub precipitation: f32,
+
+}
+
+impl TileClimate {
+
+    pub const fn new(temperature: f32, precipitation: f32) -> Self {
+
+        Self {
+            temperature,
+            precipitation,
+        }
+    }
+
+}
+
+
+
+/// Settings of deriving climate from latitude, elevation and distance to sea.
+/// The map spans from the south pole at `y = 0` to the north pole at the top row.
+#[derive(Clone)]
+pub struct ClimateSettings {
+
+    pub equator_temperature: f32,
+    pub pole_temperature: f32,
+    /// Degrees lost per kilometer above the sea level.
+    pub temperature_lapse: f32,
+
+    /// Rain over the sea, in the wettest latitudes.
+    pub max_precipitation: f32,
+    /// Distance from the sea, in kilometers, over which air loses most of its moisture.
+    pub moisture_range: f32,
+    /// How much seeded noise changes precipitation, from 0 to 1, breaking up latitude bands.
+    pub variation: f32,
+
+}
+
+impl ClimateSettings {
+
+    pub fn new() -> Self {
+
+        Self {
+            equator_temperature: 27.0,
+            pole_temperature: -25.0,
+            temperature_lapse: 6.5,
+
+            max_precipitation: 2000.0,
+            moisture_range: 1500.0,
+            variation: 0.35,
+        }
+    }
+
+    /// Every value is optional, falling back to ones from `new`.
+    pub fn from_data(entry: &DataEntry) -> CoreResult<Self> {
+        let mut settings = Self::new();
+
+        let read_value = |key: &str, value: &mut f32| -> CoreResult<()> {
+            if let Some(value_entry) = entry.get_optional(key)? {
+                *value = value_entry.as_f64()? as f32;
+            }
+
+            Ok(())
+        };
+        read_value("equator_temperature", &mut settings.equator_temperature)?;
+        read_value("pole_temperature", &mut settings.pole_temperature)?;
+        read_value("temperature_lapse", &mut settings.temperature_lapse)?;
+        read_value("max_precipitation", &mut settings.max_precipitation)?;
+        read_value("moisture_range", &mut settings.moisture_range)?;
+        read_value("variation", &mut settings.variation)?;
+
+        if settings.moisture_range <= 0.0 {
+            return Err(entry.get("moisture_range")?.error("must be above 0"));
+        }
+
+        Ok(settings)
+    }
+
+
+
+    /// Overwrites climate of every tile, sea must already be known, see `HydrologySettings::apply`.
+    pub fn apply(&self, map_settings: &MapSettings, tiles: &mut [TileLocal]) {
+        let shape = map_settings.shape;
+        let (_, height) = shape.max_axis();
+        let sea_distances = Self::sea_distances(map_settings, tiles);
+        let tile_kilometers = map_settings.tile_size.to_meters() as f32 / 1000.0;
+
+        for (tile_index, tile) in tiles.iter_mut().enumerate() {
+            let coordinates = shape.coordinates(tile_index);
+            let latitude = ((coordinates.y as f32 + 0.5) / height as f32 - 0.5) * PI;
+            let elevation = (tile.main.height - map_settings.sea_level).to_meters().max(0) as f32 / 1000.0;
+
+            let temperature = self.pole_temperature + (self.equator_temperature - self.pole_temperature) * latitude.cos()
+                - self.temperature_lapse * elevation;
+
+            // Wet equator and middle latitudes, with dry belts around 30 degrees and poles, like on Earth.
+            let latitude_moisture = 0.4 + 0.3 * (1.0 + (latitude * 6.0).cos());
+            let sea_moisture = match sea_distances[tile_index] {
+                Some(distance) => 0.3 + 0.7 * (-(distance as f32 * tile_kilometers) / self.moisture_range).exp(),
+                // Maps with no sea at all are taken for inner parts of a bigger world, with moisture coming from outside.
+                None => 1.0,
+            };
+
+            let local_moisture = 1.0 + self.variation * self.precipitation_noise(map_settings, coordinates);
+
+            tile.main.climate = TileClimate::new(
+                temperature,
+                (self.max_precipitation * latitude_moisture * sea_moisture * local_moisture).max(0.0),
+            );
+        }
+    }
+
+    /// From -1 to 1, seamless across the edge of wrapping maps.
+    fn precipitation_noise(&self, map_settings: &MapSettings, coordinates: TileCoordinates) -> f32 {
+        const CELL_SIZE: f32 = 8.0;
+        let (center_x, center_y) = map_settings.shape.tile_center(coordinates);
+
+        let (x_scale, x_period) = if map_settings.shape.wraps_x() {
+            let (display_width, _) = map_settings.shape.display_size();
+            let cells = (display_width / CELL_SIZE).round().max(1.0);
+
+            (cells / display_width, Some(cells as i64))
+        } else {
+            (1.0 / CELL_SIZE, None)
+        };
+
+        fractal_noise(map_settings.seed.wrapping_add(2), center_x * x_scale, center_y / CELL_SIZE, x_period, 3)
+    }
+
+    /// Steps to the nearest sea tile, `None` with no sea at all.
+    fn sea_distances(map_settings: &MapSettings, tiles: &[TileLocal]) -> Vec<Option<u32>> {
+        let shape = map_settings.shape;
+        let mut distances = vec![None; tiles.len()];
+        let mut open = VecDeque::new();
+
+        for (tile_index, tile) in tiles.iter().enumerate() {
+            if tile.main.water.is_sea() {
+                distances[tile_index] = Some(0);
+                open.push_back(tile_index);
+            }
+        }
+
+        while let Some(tile_index) = open.pop_front() {
+            let distance = distances[tile_index].unwrap_or(0);
+
+            for neighbour in shape.adjacent(shape.coordinates(tile_index)) {
+                let neighbour_index = shape.index(neighbour);
+
+                if distances[neighbour_index].is_none() {
+                    distances[neighbour_index] = Some(distance + 1);
+                    open.push_back(neighbour_index);
+                }
+            }
+        }
+
+        distances
+    }
+
+}
+
+impl Default for ClimateSettings {
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+
+
+/// Surface type for tiles within the climate bounds, every bound is optional.
+#[derive(Clone)]
+pub struct Biome {
+
+    pub name: String,
+    pub layer: usize,
+    pub type_id: usize,
+
+    pub min_temperature: Option<f32>,
+    pub max_temperature: Option<f32>,
+    pub min_precipitation: Option<f32>,
+    pub max_precipitation: Option<f32>,
+
+}
+
+impl Biome {
+
+    pub fn from_data(entry: &DataEntry, surface_types: &SurfaceTypes) -> CoreResult<Self> {
+        let layer_entry = entry.get("layer")?;
+        let layer = layer_entry.as_usize()?;
+        let layers = &surface_types.layers;
+        if layer >= layers.len() {
+            return Err(layer_entry.error(format!("there are only {} surface layers", layers.len())));
+        }
+
+        let type_entry = entry.get("type")?;
+        let type_name = type_entry.as_str()?;
+        let type_id = layers[layer].type_id(type_name)
+            .ok_or_else(|| type_entry.error(format!("names unknown surface type `{type_name}` in layer {layer}")))?;
+
+        let optional_bound = |key: &str| -> CoreResult<Option<f32>> {
+            match entry.get_optional(key)? {
+                Some(bound_entry) => Ok(Some(bound_entry.as_f64()? as f32)),
+                None => Ok(None),
+            }
+        };
+
+        Ok(Self {
+            name: entry.get("name")?.as_str()?.to_string(),
+            layer,
+            type_id,
+
+            min_temperature: optional_bound("min_temperature")?,
+            max_temperature: optional_bound("max_temperature")?,
+            min_precipitation: optional_bound("min_precipitation")?,
+            max_precipitation: optional_bound("max_precipitation")?,
+        })
+    }
+
+    pub fn matches(&self, climate: TileClimate) -> bool {
+
+        self.min_temperature.is_none_or(|min_temperature| climate.temperature >= min_temperature)
+            && self.max_temperature.is_none_or(|max_temperature| climate.temperature <= max_temperature)
+            && self.min_precipitation.is_none_or(|min_precipitation| climate.precipitation >= min_precipitation)
+            && self.max_precipitation.is_none_or(|max_precipitation| climate.precipitation <= max_precipitation)
+    }
+
+}
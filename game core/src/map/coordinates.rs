@@ -0,0 +1,176 @@
+use std::fmt::{Display as FmtDisplay, Formatter};
+
+
+
+/// Position of a tile on the map grid, with `y` growing towards north.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct TileCoordinates {
+
+    pub x: u32,
+    pub y: u32,
+
+}
+
+impl TileCoordinates {
+
+    pub const fn new(x: u32, y: u32) -> Self {
+
+        Self {
+            x,
+            y,
+        }
+    }
+
+    pub const fn from_tuple(coordinates: (u32, u32)) -> Self {
+
+        Self::new(coordinates.0, coordinates.1)
+    }
+
+    pub const fn to_tuple(self) -> (u32, u32) {
+
+        (self.x, self.y)
+    }
+
+}
+
+impl FmtDisplay for TileCoordinates {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+
+        write!(formatter, "({}, {})", self.x, self.y)
+    }
+}
+
+
+
+/// Direction to one of the 8 tiles around a square tile.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Direction {
+
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+
+}
+
+impl Direction {
+
+    /// Tiles sharing an edge.
+    pub const ORTHOGONAL: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
+    /// Tiles sharing an edge or a corner, clockwise from north.
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    pub const fn shift(self) -> (i64, i64) {
+
+        match self {
+            Direction::North => (0, 1),
+            Direction::NorthEast => (1, 1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (1, -1),
+            Direction::South => (0, -1),
+            Direction::SouthWest => (-1, -1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (-1, 1),
+        }
+    }
+
+    pub const fn opposite(self) -> Self {
+
+        match self {
+            Direction::North => Direction::South,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::East => Direction::West,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::South => Direction::North,
+            Direction::SouthWest => Direction::NorthEast,
+            Direction::West => Direction::East,
+            Direction::NorthWest => Direction::SouthEast,
+        }
+    }
+
+    pub const fn is_diagonal(self) -> bool {
+
+        matches!(self, Direction::NorthEast | Direction::SouthEast | Direction::SouthWest | Direction::NorthWest)
+    }
+
+    /// Orthogonal directions a diagonal one lies between, `None` for orthogonal ones.
+    pub const fn components(self) -> Option<(Direction, Direction)> {
+
+        match self {
+            Direction::NorthEast => Some((Direction::North, Direction::East)),
+            Direction::SouthEast => Some((Direction::South, Direction::East)),
+            Direction::SouthWest => Some((Direction::South, Direction::West)),
+            Direction::NorthWest => Some((Direction::North, Direction::West)),
+            _ => None,
+        }
+    }
+
+}
+
+
+
+/// Direction to one of the 6 tiles around a pointy topped hexagon, see `MapShape::Hexagonal`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum HexDirection {
+
+    East,
+    NorthEast,
+    NorthWest,
+    West,
+    SouthWest,
+    SouthEast,
+
+}
+
+impl HexDirection {
+
+    /// Counterclockwise from east, so the hexagon corner `n` lies between directions `n` and `n + 1`.
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::East,
+        HexDirection::NorthEast,
+        HexDirection::NorthWest,
+        HexDirection::West,
+        HexDirection::SouthWest,
+        HexDirection::SouthEast,
+    ];
+
+    /// Odd rows are shifted towards positive x, so their diagonal neighbours are too.
+    pub const fn shift(self, row_y: u32) -> (i64, i64) {
+        let diagonal_shift = (row_y % 2) as i64;
+
+        match self {
+            HexDirection::East => (1, 0),
+            HexDirection::NorthEast => (diagonal_shift, 1),
+            HexDirection::NorthWest => (diagonal_shift - 1, 1),
+            HexDirection::West => (-1, 0),
+            HexDirection::SouthWest => (diagonal_shift - 1, -1),
+            HexDirection::SouthEast => (diagonal_shift, -1),
+        }
+    }
+
+    pub const fn opposite(self) -> Self {
+
+        match self {
+            HexDirection::East => HexDirection::West,
+            HexDirection::NorthEast => HexDirection::SouthWest,
+            HexDirection::NorthWest => HexDirection::SouthEast,
+            HexDirection::West => HexDirection::East,
+            HexDirection::SouthWest => HexDirection::NorthEast,
+            HexDirection::SouthEast => HexDirection::NorthWest,
+        }
+    }
+
+}
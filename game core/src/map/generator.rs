@@ -0,0 +1,379 @@
+use crate::Definitions;
+use crate::data::DataEntry;
+use crate::error::CoreResult;
+use crate::map::MapSettings;
+use crate::map::coordinates::TileCoordinates;
+use crate::map::tile::TileLocal;
+use crate::map::tile::surface::TileSurface;
+use crate::map::units::TerrainHeight;
+
+
+
+/// Settings of seeded terrain generation, the seed itself is `MapSettings::seed`.
+/// Noise is sampled in tile widths, so the same seed gives the same landscape on maps of any size.
+#[derive(Clone)]
+pub struct GeneratorSettings {
+
+    /// Rough size of continents, in tile widths.
+    pub feature_size: f32,
+    pub octaves: u32,
+    /// Height of the deepest sea bottom.
+    pub min_height: TerrainHeight,
+    /// Height of the highest mountain top.
+    pub max_height: TerrainHeight,
+    /// How much ridges rise above the inner parts of continents, from 0 to 1.
+    pub mountains: f32,
+
+    /// Checked in order, the first rule matching the tile sets its surface in the rule's layer, ahead of biomes.
+    pub surface_rules: Vec<SurfaceRule>,
+
+}
+
+impl GeneratorSettings {
+
+    pub fn new() -> Self {
+
+        Self {
+            feature_size: 24.0,
+            octaves: 5,
+            min_height: TerrainHeight::from_meters(-4_000),
+            max_height: TerrainHeight::from_meters(4_000),
+            mountains: 0.5,
+
+            surface_rules: vec![],
+        }
+    }
+
+    /// Every value is optional, falling back to ones from `new`.
+    pub fn from_data(entry: &DataEntry, definitions: &Definitions) -> CoreResult<Self> {
+        let mut settings = Self::new();
+
+        if let Some(feature_size_entry) = entry.get_optional("feature_size")? {
+            settings.feature_size = feature_size_entry.as_f64()? as f32;
+
+            if settings.feature_size <= 0.0 {
+                return Err(feature_size_entry.error("must be above 0"));
+            }
+        }
+        if let Some(octaves_entry) = entry.get_optional("octaves")? {
+            settings.octaves = octaves_entry.as_u32()?;
+
+            if settings.octaves == 0 {
+                return Err(octaves_entry.error("must be above 0"));
+            }
+        }
+        if let Some(min_height_entry) = entry.get_optional("min_height")? {
+            settings.min_height = TerrainHeight::from_meters(min_height_entry.as_i32()?);
+        }
+        if let Some(max_height_entry) = entry.get_optional("max_height")? {
+            settings.max_height = TerrainHeight::from_meters(max_height_entry.as_i32()?);
+        }
+        if let Some(mountains_entry) = entry.get_optional("mountains")? {
+            settings.mountains = mountains_entry.as_f64()? as f32;
+
+            if !(0.0..=1.0).contains(&settings.mountains) {
+                return Err(mountains_entry.error("must be from 0 to 1"));
+            }
+        }
+
+        for rule_entry in entry.optional_array("surface")? {
+            settings.surface_rules.push(SurfaceRule::from_data(&rule_entry, definitions)?);
+        }
+
+        Ok(settings)
+    }
+
+
+
+    /// Overwrites height of every tile, and surfaces of tiles matching any rule, or else any biome of `definitions`.
+    /// Water and climate are derived on the way, for biomes to use, so they don't need deriving again, unless heights change.
+    pub fn generate(&self, map_settings: &MapSettings, definitions: &Definitions, tiles: &mut [TileLocal]) {
+        let shape = map_settings.shape;
+
+        for (tile_index, tile) in tiles.iter_mut().enumerate() {
+            let coordinates = shape.coordinates(tile_index);
+
+            tile.main.height = self.height_at(map_settings, coordinates);
+        }
+        map_settings.apply_environment(tiles);
+
+        for tile_index in 0..tiles.len() {
+            let coordinates = shape.coordinates(tile_index);
+            let height = tiles[tile_index].main.height;
+            let climate = tiles[tile_index].main.climate;
+            let slope = self.slope_at(map_settings, tiles, coordinates);
+
+            for layer_index in 0..definitions.surface_types.layers.len() {
+                let matching_type = self.surface_rules.iter()
+                    .find(|rule| rule.layer == layer_index && rule.matches(height, slope))
+                    .map(|rule| rule.type_id)
+                    .or_else(|| definitions.biomes.iter()
+                        .find(|biome| biome.layer == layer_index && biome.matches(climate))
+                        .map(|biome| biome.type_id));
+
+                if let Some(type_id) = matching_type {
+                    tiles[tile_index].surface[layer_index] = TileSurface::new_automatic(type_id);
+                }
+            }
+        }
+    }
+
+    fn height_at(&self, map_settings: &MapSettings, coordinates: TileCoordinates) -> TerrainHeight {
+        let (center_x, center_y) = map_settings.shape.tile_center(coordinates);
+        let seed = map_settings.seed;
+
+        // Wrapping maps need a whole number of noise cells across, so the seam doesn't show.
+        let (x_scale, x_period) = if map_settings.shape.wraps_x() {
+            let (display_width, _) = map_settings.shape.display_size();
+            let cells = (display_width / self.feature_size).round().max(1.0);
+
+            (cells / display_width, Some(cells as i64))
+        } else {
+            (1.0 / self.feature_size, None)
+        };
+        let x = center_x * x_scale;
+        let y = center_y / self.feature_size;
+
+        let continents = fractal_noise(seed, x, y, x_period, self.octaves);
+        let ridges = 1.0 - fractal_noise(seed.wrapping_add(1), x * 2.0, y * 2.0, x_period.map(|period| period * 2), self.octaves).abs();
+        let inland = smooth_step((continents * 2.0).clamp(0.0, 1.0));
+
+        let level = (continents + self.mountains * ridges * ridges * inland).clamp(-1.0, 1.0);
+
+        if level >= 0.0 {
+            TerrainHeight::from_meters((level * self.max_height.to_meters() as f32) as i32)
+        } else {
+            TerrainHeight::from_meters((-level * self.min_height.to_meters() as f32) as i32)
+        }
+    }
+
+    /// Steepest height difference to a neighbour, per meter of distance between their centers.
+    fn slope_at(&self, map_settings: &MapSettings, tiles: &[TileLocal], coordinates: TileCoordinates) -> f32 {
+        let shape = map_settings.shape;
+        let height = tiles[shape.index(coordinates)].main.height;
+
+        shape.adjacent(coordinates)
+            .map(|neighbour| {
+                let height_difference = (tiles[shape.index(neighbour)].main.height - height).to_meters().abs() as f32;
+                let distance = shape.distance(coordinates, neighbour) * map_settings.tile_size.to_meters() as f32;
+
+                height_difference / distance
+            })
+            .fold(0.0, f32::max)
+    }
+
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+
+
+/// Surface given to tiles between the height and slope bounds, every bound is optional.
+#[derive(Clone)]
+pub struct SurfaceRule {
+
+    pub layer: usize,
+    pub type_id: usize,
+
+    pub min_height: Option<TerrainHeight>,
+    pub max_height: Option<TerrainHeight>,
+    pub min_slope: Option<f32>,
+    pub max_slope: Option<f32>,
+
+}
+
+impl SurfaceRule {
+
+    pub fn from_data(entry: &DataEntry, definitions: &Definitions) -> CoreResult<Self> {
+        let layer_entry = entry.get("layer")?;
+        let layer = layer_entry.as_usize()?;
+        let layers = &definitions.surface_types.layers;
+        if layer >= layers.len() {
+            return Err(layer_entry.error(format!("there are only {} surface layers", layers.len())));
+        }
+
+        let type_entry = entry.get("type")?;
+        let type_name = type_entry.as_str()?;
+        let type_id = layers[layer].type_id(type_name)
+            .ok_or_else(|| type_entry.error(format!("names unknown surface type `{type_name}` in layer {layer}")))?;
+
+        let optional_height = |key: &str| -> CoreResult<Option<TerrainHeight>> {
+            match entry.get_optional(key)? {
+                Some(height_entry) => Ok(Some(TerrainHeight::from_meters(height_entry.as_i32()?))),
+                None => Ok(None),
+            }
+        };
+        let optional_slope = |key: &str| -> CoreResult<Option<f32>> {
+            match entry.get_optional(key)? {
+                Some(slope_entry) => Ok(Some(slope_entry.as_f64()? as f32)),
+                None => Ok(None),
+            }
+        };
+
+        Ok(Self {
+            layer,
+            type_id,
+
+            min_height: optional_height("min_height")?,
+            max_height: optional_height("max_height")?,
+            min_slope: optional_slope("min_slope")?,
+            max_slope: optional_slope("max_slope")?,
+        })
+    }
+
+    pub fn matches(&self, height: TerrainHeight, slope: f32) -> bool {
+        let meters = height.to_meters();
+
+        self.min_height.is_none_or(|min_height| meters >= min_height.to_meters())
+            && self.max_height.is_none_or(|max_height| meters <= max_height.to_meters())
+            && self.min_slope.is_none_or(|min_slope| slope >= min_slope)
+            && self.max_slope.is_none_or(|max_slope| slope <= max_slope)
+    }
+
+}
+
+
+
+/// Mixes the seed with coordinates and a salt into a well spread number,
+/// the same on every platform, so maps don't depend on where they are generated.
+pub fn hash_coordinates(seed: u64, x: i64, y: i64, salt: u64) -> u64 {
+    let mut hash = seed ^ 0x9E37_79B9_7F4A_7C15;
+
+    for part in [x as u64, y as u64, salt] {
+        hash = (hash ^ part).wrapping_add(0x9E37_79B9_7F4A_7C15);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        hash ^= hash >> 31;
+    }
+
+    hash
+}
+
+/// Value noise summed over octaves, each one twice as detailed and half as strong, from -1 to 1.
+pub fn fractal_noise(seed: u64, x: f32, y: f32, x_period: Option<i64>, octaves: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude_sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+
+    for octave in 0..octaves {
+        let octave_period = x_period.map(|period| period * frequency as i64);
+
+        sum += value_noise(seed.wrapping_add(octave as u64), x * frequency, y * frequency, octave_period) * amplitude;
+        amplitude_sum += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    sum / amplitude_sum
+}
+
+/// Random values at whole coordinates, smoothly blended in between, from -1 to 1.
+/// With `x_period`, the noise repeats every that many cells along x.
+pub fn value_noise(seed: u64, x: f32, y: f32, x_period: Option<i64>) -> f32 {
+    let cell_x = x.floor();
+    let cell_y = y.floor();
+    let blend_x = smooth_step(x - cell_x);
+    let blend_y = smooth_step(y - cell_y);
+
+    let corner = |x_shift: i64, y_shift: i64| {
+        let mut corner_x = cell_x as i64 + x_shift;
+        if let Some(period) = x_period {
+            corner_x = corner_x.rem_euclid(period);
+        }
+
+        let hash = hash_coordinates(seed, corner_x, cell_y as i64 + y_shift, 0);
+
+        (hash >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    };
+
+    let south = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * blend_x;
+    let north = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * blend_x;
+
+    south + (north - south) * blend_y
+}
+
+fn smooth_step(value: f32) -> f32 {
+
+    value * value * (3.0 - 2.0 * value)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::{Path, PathBuf};
+    use crate::data::DataFile;
+    use crate::map::MapShape;
+    use super::*;
+
+    fn definitions() -> Definitions {
+
+        Definitions::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../game sets/historical")).unwrap()
+    }
+
+    fn generate(definitions: &Definitions, shape: MapShape, seed: u64) -> Vec<TileLocal> {
+        let mut map_settings = MapSettings::new(TerrainHeight::from_meters(1000), shape);
+        map_settings.seed = seed;
+        let filler = TileLocal::new(0, vec![TileSurface::new_automatic(0), TileSurface::EMPTY].into_boxed_slice());
+        let mut tiles = vec![filler; shape.tile_amount() as usize];
+
+        GeneratorSettings::new().generate(&map_settings, definitions, &mut tiles);
+        tiles
+    }
+
+    fn heights(tiles: &[TileLocal]) -> Vec<i32> {
+
+        tiles.iter().map(|tile| tile.main.height.to_meters()).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_map() {
+        let definitions = definitions();
+
+        for shape in [MapShape::Rectangular { width: 48, height: 24 }, MapShape::Cylindrical { width: 48, height: 24 }] {
+            let first = generate(&definitions, shape, 1918);
+            let second = generate(&definitions, shape, 1918);
+
+            assert_eq!(heights(&first), heights(&second));
+            for (first_tile, second_tile) in first.iter().zip(&second) {
+                assert_eq!(first_tile.surface, second_tile.surface);
+                assert_eq!(first_tile.main.water, second_tile.main.water);
+                assert_eq!(first_tile.main.climate, second_tile.main.climate);
+            }
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_maps() {
+        let definitions = definitions();
+        let shape = MapShape::Rectangular { width: 48, height: 24 };
+        let first = heights(&generate(&definitions, shape, 1918));
+        let second = heights(&generate(&definitions, shape, 1939));
+
+        let different_tiles = first.iter().zip(&second).filter(|(first_height, second_height)| first_height != second_height).count();
+        assert!(different_tiles > first.len() / 2, "only {different_tiles} tiles differ");
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let definitions = definitions();
+
+        for text in ["octaves = 0", "feature_size = 0", "mountains = -0.1", "mountains = 1.5"] {
+            let file = DataFile::parse(PathBuf::from("generator.toml"), text).unwrap();
+            assert!(GeneratorSettings::from_data(&file.root(), &definitions).is_err(), "`{text}` was accepted");
+        }
+
+        let file = DataFile::parse(PathBuf::from("generator.toml"), "octaves = 1\nmountains = 1.0").unwrap();
+        let settings = GeneratorSettings::from_data(&file.root(), &definitions).unwrap();
+        assert_eq!(settings.octaves, 1);
+        assert_eq!(settings.mountains, 1.0);
+    }
+
+}
@@ -0,0 +1,233 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use crate::data::DataEntry;
+use crate::error::CoreResult;
+use crate::map::MapSettings;
+use crate::map::coordinates::TileCoordinates;
+use crate::map::tile::{TileLocal, TileWater};
+use crate::map::units::TerrainHeight;
+
+
+
+/// Settings of deriving sea, lakes and rivers from tile heights, the sea level itself is `MapSettings::sea_level`.
+#[derive(Clone)]
+pub struct HydrologySettings {
+
+    /// How many tiles must drain through a land tile, itself included, for a river to flow over it.
+    pub river_flow: u32,
+    /// Shallower pits are filled with water just to let rivers flow through them, without becoming lakes.
+    pub min_lake_depth: TerrainHeight,
+
+}
+
+impl HydrologySettings {
+
+    pub fn new() -> Self {
+
+        Self {
+            river_flow: 24,
+            min_lake_depth: TerrainHeight::from_meters(20),
+        }
+    }
+
+    /// Every value is optional, falling back to ones from `new`.
+    pub fn from_data(entry: &DataEntry) -> CoreResult<Self> {
+        let mut settings = Self::new();
+
+        if let Some(river_flow_entry) = entry.get_optional("river_flow")? {
+            let river_flow = river_flow_entry.as_u32()?;
+            // Every tile drains at least itself, so it would be all rivers.
+            if river_flow == 0 {
+                return Err(river_flow_entry.error("must be above 0"));
+            }
+
+            settings.river_flow = river_flow;
+        }
+        if let Some(lake_depth_entry) = entry.get_optional("min_lake_depth")? {
+            let min_lake_depth = lake_depth_entry.as_i32()?;
+            // Every filled pit would be deep enough, flooding whole continents.
+            if min_lake_depth <= 0 {
+                return Err(lake_depth_entry.error("must be above 0"));
+            }
+
+            settings.min_lake_depth = TerrainHeight::from_meters(min_lake_depth);
+        }
+
+        Ok(settings)
+    }
+
+
+
+    /// Overwrites water of every tile.
+    /// Tiles below the sea level are sea, everything else drains either into the sea, or over the map edge.
+    /// Pits are flooded up to their lowest outlet, becoming lakes if they're deep enough.
+    pub fn apply(&self, map_settings: &MapSettings, tiles: &mut [TileLocal]) {
+        let shape = map_settings.shape;
+        let sea_level = map_settings.sea_level.to_meters();
+
+        // Flooding from outlets up, always continuing from the lowest water level reached,
+        // leads every tile towards the outlet, through which it drains.
+        let mut water_levels: Vec<Option<i32>> = vec![None; tiles.len()];
+        let mut downstream: Vec<Option<usize>> = vec![None; tiles.len()];
+        let mut drain_order = Vec::with_capacity(tiles.len());
+        let mut open = BinaryHeap::new();
+
+        for (tile_index, tile) in tiles.iter().enumerate() {
+            let height = tile.main.height.to_meters();
+
+            if height < sea_level || self.is_on_edge(map_settings, shape.coordinates(tile_index)) {
+                water_levels[tile_index] = Some(height.max(sea_level));
+                open.push(Reverse((height.max(sea_level), tile_index)));
+            }
+        }
+
+        while let Some(Reverse((water_level, tile_index))) = open.pop() {
+            drain_order.push(tile_index);
+
+            for neighbour in shape.adjacent(shape.coordinates(tile_index)) {
+                let neighbour_index = shape.index(neighbour);
+                if water_levels[neighbour_index].is_some() {
+                    continue;
+                }
+
+                let neighbour_level = tiles[neighbour_index].main.height.to_meters().max(water_level);
+                water_levels[neighbour_index] = Some(neighbour_level);
+                downstream[neighbour_index] = Some(tile_index);
+                open.push(Reverse((neighbour_level, neighbour_index)));
+            }
+        }
+
+        let is_sea = |tile: &TileLocal| tile.main.height.to_meters() < sea_level;
+
+        // Upstream tiles come last in the drain order, so going backwards passes all the flow down.
+        let mut flows: Vec<u32> = tiles.iter().map(|tile| if is_sea(tile) { 0 } else { 1 }).collect();
+        for &tile_index in drain_order.iter().rev() {
+            if let Some(downstream_index) = downstream[tile_index] {
+                flows[downstream_index] += flows[tile_index];
+            }
+        }
+
+        let mut waters = vec![TileWater::NONE; tiles.len()];
+        for (tile_index, tile) in tiles.iter().enumerate() {
+            let height = tile.main.height.to_meters();
+            let water = &mut waters[tile_index];
+
+            if is_sea(tile) {
+                water.insert(TileWater::SEA);
+            } else if water_levels[tile_index].is_some_and(|water_level| water_level - height >= self.min_lake_depth.to_meters()) {
+                water.insert(TileWater::LAKE);
+            } else if flows[tile_index] >= self.river_flow {
+                water.insert(TileWater::RIVER);
+            }
+
+            if !is_sea(tile) && shape.adjacent(shape.coordinates(tile_index)).any(|neighbour| is_sea(&tiles[shape.index(neighbour)])) {
+                water.insert(TileWater::COAST);
+            }
+        }
+
+        for (tile, water) in tiles.iter_mut().zip(waters) {
+            tile.main.water = water;
+        }
+    }
+
+    fn is_on_edge(&self, map_settings: &MapSettings, coordinates: TileCoordinates) -> bool {
+        let (width, height) = map_settings.shape.max_axis();
+
+        coordinates.y == 0 || coordinates.y + 1 == height
+            || (!map_settings.shape.wraps_x() && (coordinates.x == 0 || coordinates.x + 1 == width))
+    }
+
+}
+
+impl Default for HydrologySettings {
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::PathBuf;
+    use crate::data::DataFile;
+    use crate::map::MapShape;
+    use super::*;
+
+    fn test_tiles(shape: MapShape, height: impl Fn(TileCoordinates) -> i32) -> Vec<TileLocal> {
+
+        (0..shape.tile_amount() as usize).map(|tile_index| {
+            let mut tile = TileLocal::new(0, Box::default());
+            tile.main.height = TerrainHeight::from_meters(height(shape.coordinates(tile_index)));
+            tile
+        }).collect()
+    }
+
+    fn water(shape: MapShape, tiles: &[TileLocal], x: u32, y: u32) -> TileWater {
+
+        tiles[shape.raw_index(x, y)].main.water
+    }
+
+    #[test]
+    fn deep_pits_become_lakes() {
+        let shape = MapShape::Rectangular { width: 7, height: 5 };
+        let map_settings = MapSettings::new(TerrainHeight::from_meters(1000), shape);
+        // A pit 50 meters deep, and one 10 meters deep, both surrounded by 100 meters high land.
+        let mut tiles = test_tiles(shape, |coordinates| match coordinates.to_tuple() {
+            (2, 2) => 50,
+            (4, 2) => 90,
+            _ => 100,
+        });
+
+        map_settings.hydrology.apply(&map_settings, &mut tiles);
+        assert!(water(shape, &tiles, 2, 2).is_lake());
+        assert!(!water(shape, &tiles, 4, 2).is_lake());
+        assert_eq!(tiles.iter().filter(|tile| tile.main.water.is_open_water()).count(), 1);
+    }
+
+    #[test]
+    fn sea_and_coast_follow_the_sea_level() {
+        let shape = MapShape::Rectangular { width: 5, height: 3 };
+        let map_settings = MapSettings::new(TerrainHeight::from_meters(1000), shape);
+        let mut tiles = test_tiles(shape, |coordinates| if coordinates.x < 2 { -10 } else { 50 });
+
+        map_settings.hydrology.apply(&map_settings, &mut tiles);
+        assert!(water(shape, &tiles, 1, 1).is_sea());
+        assert!(!water(shape, &tiles, 1, 1).is_coast());
+        assert!(water(shape, &tiles, 2, 1).is_coast());
+        assert!(!water(shape, &tiles, 3, 1).is_coast());
+    }
+
+    #[test]
+    fn rivers_reach_the_map_edge() {
+        let shape = MapShape::Rectangular { width: 8, height: 5 };
+        let mut map_settings = MapSettings::new(TerrainHeight::from_meters(1000), shape);
+        map_settings.hydrology.river_flow = 4;
+        // Valley along the middle row, falling towards the west edge.
+        let mut tiles = test_tiles(shape, |coordinates| if coordinates.y == 2 { 100 + coordinates.x as i32 * 10 } else { 500 });
+
+        map_settings.hydrology.apply(&map_settings, &mut tiles);
+        for x in 0..8 {
+            // The highest valley tile drains just its own 3 tiles, too little for a river,
+            // the east edge one drains only itself.
+            assert_eq!(water(shape, &tiles, x, 2).is_river(), x < 6, "tile ({x}, 2)");
+            assert!(!water(shape, &tiles, x, 1).is_river());
+        }
+    }
+
+    #[test]
+    fn zero_settings_are_rejected() {
+        for text in ["river_flow = 0", "min_lake_depth = 0", "min_lake_depth = -5"] {
+            let file = DataFile::parse(PathBuf::from("hydrology.toml"), text).unwrap();
+            assert!(HydrologySettings::from_data(&file.root()).is_err(), "`{text}` was accepted");
+        }
+
+        let file = DataFile::parse(PathBuf::from("hydrology.toml"), "river_flow = 1\nmin_lake_depth = 1").unwrap();
+        let settings = HydrologySettings::from_data(&file.root()).unwrap();
+        assert_eq!(settings.river_flow, 1);
+        assert_eq!(settings.min_lake_depth.to_meters(), 1);
+    }
+
+}
@@ -0,0 +1,233 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use crate::Definitions;
+use crate::data::DataEntry;
+use crate::error::{CoreError, CoreResult, ResultToCoreError};
+use crate::image::{Image, ImageDimensions};
+use crate::image::color::{ColorFn, Grey16, Rgb8, Rgba8};
+use crate::map::MapSettings;
+use crate::map::coordinates::TileCoordinates;
+use crate::map::tile::TileLocal;
+use crate::map::tile::surface::TileSurface;
+use crate::map::units::TerrainHeight;
+use crate::powers::Power;
+
+
+
+/// Images describing a map with one pixel per tile, north being at the top, every one is optional.
+pub struct MapImages {
+
+    pub heightmap: Option<HeightmapImage>,
+    /// Surface types, each image covering single layer.
+    pub surfaces: Vec<(usize, ColorKeyImage)>,
+    /// Power indices.
+    pub owners: Option<ColorKeyImage>,
+
+}
+
+impl MapImages {
+
+    pub fn new() -> Self {
+
+        Self {
+            heightmap: None,
+            surfaces: vec![],
+            owners: None,
+        }
+    }
+
+    /// Image paths are relative to `directory`, power and surface type names are resolved right away.
+    pub fn from_data(entry: &DataEntry, directory: &Path, definitions: &Definitions, powers: &[Power]) -> CoreResult<Self> {
+        let mut images = Self::new();
+
+        if let Some(heightmap_entry) = entry.get_optional("heightmap")? {
+            images.heightmap = Some(HeightmapImage::load(
+                directory.join(heightmap_entry.as_str()?),
+                TerrainHeight::from_meters(entry.get("min_height")?.as_i32()?),
+                TerrainHeight::from_meters(entry.get("max_height")?.as_i32()?),
+            )?);
+        }
+
+        if let Some(owners_entry) = entry.get_optional("owners")? {
+            let mut key = vec![];
+
+            for (color_text, name_entry) in entry.get("owner_key")?.table_entries()? {
+                let name = name_entry.as_str()?;
+                let power = powers.iter().position(|power| power.name() == name)
+                    .ok_or_else(|| name_entry.error(format!("names unknown power `{name}`")))?;
+
+                key.push((Self::key_color(&name_entry, color_text)?, power));
+            }
+
+            images.owners = Some(ColorKeyImage::load(directory.join(owners_entry.as_str()?), key)?);
+        }
+
+        for surface_entry in entry.optional_array("surface")? {
+            let layer_entry = surface_entry.get("layer")?;
+            let layer = layer_entry.as_usize()?;
+            let layers = &definitions.surface_types.layers;
+            if layer >= layers.len() {
+                return Err(layer_entry.error(format!("there are only {} surface layers", layers.len())));
+            }
+
+            let mut key = vec![];
+            for (color_text, type_entry) in surface_entry.get("key")?.table_entries()? {
+                let type_name = type_entry.as_str()?;
+                let type_id = layers[layer].type_id(type_name)
+                    .ok_or_else(|| type_entry.error(format!("names unknown surface type `{type_name}` in layer {layer}")))?;
+
+                key.push((Self::key_color(&type_entry, color_text)?, type_id));
+            }
+
+            images.surfaces.push((layer, ColorKeyImage::load(directory.join(surface_entry.get("image")?.as_str()?), key)?));
+        }
+
+        Ok(images)
+    }
+
+
+
+    /// Every image must be exactly as big as the map.
+    /// Images only name surface types, so their variants are left automatic.
+    pub fn apply(&self, map_settings: &MapSettings, tiles: &mut [TileLocal]) -> CoreResult<()> {
+        let shape = map_settings.shape;
+
+        if let Some(heightmap) = &self.heightmap {
+            check_dimensions(&heightmap.path, &heightmap.image, map_settings)?;
+
+            for (tile_index, tile) in tiles.iter_mut().enumerate() {
+                tile.main.height = heightmap.height_at(shape.coordinates(tile_index));
+            }
+        }
+
+        if let Some(owners) = &self.owners {
+            check_dimensions(&owners.path, &owners.image, map_settings)?;
+
+            for (tile_index, tile) in tiles.iter_mut().enumerate() {
+                tile.main.owner = owners.value_at(shape.coordinates(tile_index))?;
+            }
+        }
+
+        for (layer, surfaces) in &self.surfaces {
+            check_dimensions(&surfaces.path, &surfaces.image, map_settings)?;
+
+            for (tile_index, tile) in tiles.iter_mut().enumerate() {
+                let type_id = surfaces.value_at(shape.coordinates(tile_index))?;
+
+                tile.surface[*layer] = TileSurface::new_automatic(type_id);
+            }
+        }
+
+        Ok(())
+    }
+
+
+
+    fn key_color(entry: &DataEntry, color_text: &str) -> CoreResult<Rgb8> {
+
+        Rgb8::from_hex(color_text).ok_or_else(|| entry.error(format!("has key `{color_text}`, which isn't a color like `#ff8000`")))
+    }
+
+}
+
+impl Default for MapImages {
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+
+
+/// Grayscale image, with black being `min_height` and white `max_height`.
+/// 16 bit images keep their full precision.
+pub struct HeightmapImage {
+
+    pub path: PathBuf,
+    pub image: Image<Grey16>,
+    pub min_height: TerrainHeight,
+    pub max_height: TerrainHeight,
+
+}
+
+impl HeightmapImage {
+
+    pub fn load(path: PathBuf, min_height: TerrainHeight, max_height: TerrainHeight) -> CoreResult<Self> {
+
+        Ok(Self {
+            image: load_image_file(&path)?,
+            path,
+            min_height,
+            max_height,
+        })
+    }
+
+    fn height_at(&self, coordinates: TileCoordinates) -> TerrainHeight {
+        let grey = self.image.pixel(ImageDimensions::from_u32_tuple(coordinates.to_tuple())).unwrap_or(Grey16::BLACK);
+        let height_range = (self.max_height - self.min_height).to_meters() as i64;
+
+        self.min_height + TerrainHeight::from_meters((grey.value() as i64 * height_range / u16::MAX as i64) as i32)
+    }
+
+}
+
+
+
+/// Image with every color standing for some value, like a surface type id, or a power index.
+pub struct ColorKeyImage {
+
+    pub path: PathBuf,
+    pub image: Image<Rgb8>,
+    pub key: Vec<(Rgb8, usize)>,
+
+}
+
+impl ColorKeyImage {
+
+    pub fn load(path: PathBuf, key: Vec<(Rgb8, usize)>) -> CoreResult<Self> {
+
+        Ok(Self {
+            image: load_image_file(&path)?,
+            path,
+            key,
+        })
+    }
+
+    pub(crate) fn value_at(&self, coordinates: TileCoordinates) -> CoreResult<usize> {
+        let color = self.image.pixel(ImageDimensions::from_u32_tuple(coordinates.to_tuple())).unwrap_or(Rgb8::BLACK);
+
+        self.key.iter()
+            .find(|(key_color, _)| *key_color == color)
+            .map(|(_, value)| *value)
+            .ok_or_else(|| CoreError::UnknownMapImageColor {
+                path: self.path.clone(),
+                x: coordinates.x,
+                y: coordinates.y,
+                color: color.to_hex(),
+            })
+    }
+
+}
+
+
+
+/// Flipped on y, so the bottom row of pixels is `y = 0`, like on the map.
+pub(crate) fn load_image_file<Color: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8>>(path: &Path) -> CoreResult<Image<Color>> {
+    let reader = BufReader::new(File::open(path).to_core_error()?);
+    let mut image = Image::load_png(reader).to_core_error()?;
+
+    image.invert_on_y();
+
+    Ok(image)
+}
+
+pub(crate) fn check_dimensions<Color: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8>>(path: &Path, image: &Image<Color>, map_settings: &MapSettings) -> CoreResult<()> {
+    let expected = map_settings.shape.max_axis_dimension();
+
+    if image.dimensions() != expected {
+        return Err(CoreError::MapImageDimensionsDontMatch { path: path.to_path_buf(), expected, found: image.dimensions() });
+    }
+
+    Ok(())
+}
@@ -1,14 +1,38 @@
+pub mod climate;
+pub mod coordinates;
+pub mod generator;
+pub mod hydrology;
+pub mod import;
+pub mod ownership;
+pub mod pathfinding;
+pub mod provinces;
 pub mod tile;
 pub mod units;
 
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
 use std::sync::Arc;
 use crate::map::units::{Distance, TerrainHeight};
-use crate::units::Time;
-use crate::{Definitions, Game};
+use crate::units::{GameDate, TimeSpan};
+use crate::Definitions;
 use crate::image::{Image, ImageDimensions};
-use crate::image::color::Rgb8;
-use crate::map::tile::{TileArray, TileLocal};
+use crate::image::color::{ColorFn, Grey16, Rgb8, Rgba8};
+use crate::data::DataEntry;
+use crate::error::{CoreError, CoreResult, ResultToCoreError};
+use crate::map::climate::{ClimateSettings, TileClimate};
+use crate::map::coordinates::{Direction, HexDirection, TileCoordinates};
+use crate::map::generator::GeneratorSettings;
+use crate::map::hydrology::HydrologySettings;
+use crate::map::import::MapImages;
+use crate::map::ownership::{BorderEdges, OwnershipTransfer, TransferReason};
+use crate::map::provinces::Provinces;
+use crate::map::tile::{TileLink, TileLocal, TileWater};
+use crate::map::tile::sectors::SectorHarvest;
 use crate::map::tile::surface::TileSurface;
+use crate::map::tile::chunks::{ChunkCoordinates, TileChunks};
+use crate::powers::Power;
+use crate::scenario::Scenario;
 
 
 
@@ -17,99 +41,277 @@ pub struct Map {
     definitions: Arc<Definitions>,
     pub properties: MapSettings,
 
-    tiles: TileArray,
+    tiles: TileChunks,
+    pub provinces: Provinces,
+
+    ownership_history: Vec<OwnershipTransfer>,
+    borders: BorderEdges,
+
+    /// Latest harvest of every tile sector type, `None` until its first reward date.
+    harvests: Vec<Option<SectorHarvest>>,
 
 }
 
 impl Map {
 
-    pub fn new(definitions: Arc<Definitions>, properties: MapSettings, _start_time: Time) -> Self {
-        let tile_amount = properties.shape.tile_amount() as usize;
-        let mut tile_array = TileArray::new(
-            definitions.clone(),
-            TileLocal::new(3, Box::new([TileSurface::new(0, 3)])),
-            tile_amount as u32
-        );
+    /// Blank map, with every tile being a clone of `filler`.
+    pub fn new(definitions: Arc<Definitions>, properties: MapSettings, filler: TileLocal) -> Self {
+        let tiles = TileChunks::new(definitions.clone(), properties.shape, filler);
+
+        Self {
+            provinces: Provinces::new_empty(properties.shape),
+            ownership_history: vec![],
+            borders: BorderEdges::new(properties.shape, &tiles),
+            harvests: vec![None; definitions.tile_sector_types.len()],
+            tiles,
+            definitions,
+            properties,
+        }
+    }
 
-        for tile_y in 0..30 {
-            for tile_x in 0..30 {
-                let tile_index = (tile_y * 30 + tile_x) as u32;
-                let mut result_tile = tile_array.index(tile_index).to_local();
+    /// Map of `filler` tiles, with generated terrain, using default `GeneratorSettings` if `properties` have none.
+    pub fn generate(definitions: Arc<Definitions>, properties: MapSettings, filler: TileLocal) -> Self {
+        let mut tiles = vec![filler.clone(); properties.shape.tile_amount() as usize];
+        let generator = properties.generator.clone().unwrap_or_default();
 
-                let east_dessert = (tile_y - 17).max(17 - tile_y)
-                    + 10 - tile_x / 3;
+        generator.generate(&properties, &definitions, &mut tiles);
 
-                if east_dessert < 7 {
-                    result_tile.surface[0] = TileSurface::new(1, 0);
-                }
+        Self::from_tiles(definitions, properties, filler, &tiles)
+    }
 
-                let from_center_distance = (tile_x - 15).max(15 - tile_x) + (tile_y - 15).max(15 - tile_y);
-                let from_first_mountain_pass_distance = (tile_x - 12).max(12 - tile_x) + (tile_y - 12).max(12 - tile_y);
-                let from_second_mountain_pass_distance = (tile_x - 11).max(11 - tile_x) + (tile_y - 10).max(10 - tile_y);
-                let from_second_mountain_distance = (tile_x - 8).max(8 - tile_x) + (tile_y - 6).max(6 - tile_y);
-
-                let from_mountain_distance = from_center_distance
-                    .min(from_second_mountain_distance)
-                    .min(from_first_mountain_pass_distance + 2)
-                    .min(from_second_mountain_pass_distance + 2);
-
-                if from_mountain_distance < 6 {
-                    let mut height = (6 - from_mountain_distance) * 10;
-                    height *= height;
-                    result_tile.main.height += TerrainHeight::from_meters(height);
-                    result_tile.surface[0] = TileSurface::new(2, 2 - from_mountain_distance as usize / 2);
-                }
+    /// Map of `filler` tiles, with whatever the images describe drawn over them.
+    pub fn from_images(definitions: Arc<Definitions>, properties: MapSettings, filler: TileLocal, images: &MapImages) -> CoreResult<Self> {
+        let mut tiles = vec![filler.clone(); properties.shape.tile_amount() as usize];
 
-                let _ = tile_array.put(tile_index, result_tile);
-            }
-        }
+        images.apply(&properties, &mut tiles)?;
+        properties.apply_environment(&mut tiles);
+
+        Ok(Self::from_tiles(definitions, properties, filler, &tiles))
+    }
+
+    pub fn from_scenario(definitions: Arc<Definitions>, scenario: &Scenario) -> Self {
+        // Scenarios have every tile, so the filler is never seen.
+        let filler = TileLocal::new(0, Box::default());
+        let mut map = Self::from_tiles(definitions, scenario.map_settings.clone(), filler, &scenario.tiles);
+        map.provinces = scenario.provinces.clone();
+
+        map
+    }
+
+    fn from_tiles(definitions: Arc<Definitions>, properties: MapSettings, filler: TileLocal, tiles: &[TileLocal]) -> Self {
+        let tile_chunks = TileChunks::from_tiles(definitions.clone(), properties.shape, filler, tiles);
 
         Self {
+            provinces: Provinces::new_empty(properties.shape),
+            ownership_history: vec![],
+            borders: BorderEdges::new(properties.shape, &tile_chunks),
+            harvests: vec![None; definitions.tile_sector_types.len()],
             definitions,
             properties,
-            tiles: tile_array,
-            //tile_sectors,
+            tiles: tile_chunks,
         }
     }
 
 
 
-    pub fn tick(&mut self, _tick_length: Time) {
+    /// Sector types, whose reward date passed during the tick ending at `current_time`, harvest the map.
+    pub fn tick(&mut self, current_time: GameDate, tick_length: TimeSpan) {
 
-        /*for (sector_type_index, sector_tiles) in self.tile_sectors.iter_mut().enumerate() {
+        for (sector_type_index, sector_type) in self.definitions.tile_sector_types.iter().enumerate() {
+            if sector_type.has_rewards_between(current_time - tick_length, current_time) {
+                self.harvests[sector_type_index] = Some(SectorHarvest::collect(sector_type, current_time, &self.tiles));
+            }
+        }
+    }
 
-            if sector_tiles.rewards_condition(definitions, sector_type_index, tick_length) {
+    /// `None` for unknown sector types, or before their first reward date.
+    pub fn harvest(&self, sector_type: usize) -> Option<&SectorHarvest> {
 
-                // TODO rewards.
+        self.harvests.get(sector_type)?.as_ref()
+    }
 
+
+
+    /// Territories filled with owner colors, flags scaled into the largest rectangle of each territory, and dark borders.
+    /// Oriented like `SurfaceTypes::build_surface_texture`, with the bottom row first, so it can be used as a texture.
+    /// Every tile is drawn as a square, whatever the map shape is.
+    pub fn political_map_image(&self, powers: &[Power], tile_image_size: ImageDimensions) -> CoreResult<Image<Rgb8>> {
+        let shape = self.properties.shape;
+        let mut image = Image::new_uniform(Rgb8::BLACK, self.image_dimensions(tile_image_size));
+
+        for (tile_index, tile) in self.tiles.into_iter().enumerate() {
+            let color = powers.get(tile.main.owner).map_or(Rgb8::BLACK, |power| power.color());
+
+            image.fill_rectangle(color, self.tile_image_position(tile_index) * tile_image_size, tile_image_size);
+        }
+
+        for (power_index, power) in powers.iter().enumerate() {
+            let Some(flag) = power.flag() else { continue };
+            let Some((rectangle_offset, rectangle_size)) = self.largest_territory_rectangle(power_index) else { continue };
+
+            // Flag keeps its proportions, with some of the territory color left around it.
+            let area_size = rectangle_size * tile_image_size;
+            let area_offset = rectangle_offset * tile_image_size;
+            let flag_size = flag.dimensions();
+            let scale = (area_size.x as f32 / flag_size.x as f32).min(area_size.y as f32 / flag_size.y as f32) * 0.75;
+            let draw_size = ImageDimensions::new((flag_size.x as f32 * scale) as usize, (flag_size.y as f32 * scale) as usize);
+
+            if draw_size.x > 0 && draw_size.y > 0 {
+                let draw_offset = ImageDimensions::new(
+                    area_offset.x + (area_size.x - draw_size.x) / 2,
+                    area_offset.y + (area_size.y - draw_size.y) / 2,
+                );
+
+                image.overdraw_image_scaled(flag, draw_offset, draw_size).to_core_error()?;
             }
+        }
+
+        let border_width = (tile_image_size.x.min(tile_image_size.y) / 16).max(1);
+        for (tile_index, tile) in self.tiles.into_iter().enumerate() {
+            let coordinates = shape.coordinates(tile_index);
+            let tile_offset = self.tile_image_position(tile_index) * tile_image_size;
 
-        }*/
+            for (neighbour, neighbour_tile) in self.tiles.neighbours(coordinates) {
+                if neighbour_tile.main.owner == tile.main.owner {
+                    continue;
+                }
 
+                // Each side of a border draws its own half.
+                // Hexagonal neighbours in other rows only get their half of the top, or the bottom side.
+                let (line_offset, line_size) = match shape.image_side(coordinates, neighbour) {
+                    (x_side, 0) => (
+                        ImageDimensions::new(if x_side > 0 { tile_image_size.x - border_width } else { 0 }, 0),
+                        ImageDimensions::new(border_width, tile_image_size.y),
+                    ),
+                    (x_side, y_side) => {
+                        let half_width = tile_image_size.x / 2;
+                        let (line_x, line_width) = match x_side {
+                            0 => (0, tile_image_size.x),
+                            x_side if x_side > 0 => (half_width, tile_image_size.x - half_width),
+                            _ => (0, half_width),
+                        };
+
+                        (
+                            ImageDimensions::new(line_x, if y_side > 0 { tile_image_size.y - border_width } else { 0 }),
+                            ImageDimensions::new(line_width, border_width),
+                        )
+                    },
+                };
+
+                image.fill_rectangle(Self::BORDER_COLOR, tile_offset + line_offset, line_size);
+            }
+        }
+
+        Ok(image)
     }
 
+    const BORDER_COLOR: Rgb8 = Rgb8::new(24, 24, 24);
 
+    /// Biggest rectangle of tiles all owned by the power, as its corner and size in tiles.
+    fn largest_territory_rectangle(&self, power_index: usize) -> Option<(ImageDimensions, ImageDimensions)> {
+        let (width, height) = self.properties.shape.max_axis();
+        let mut column_heights = vec![0_usize; width as usize];
+        let mut largest: Option<(ImageDimensions, ImageDimensions)> = None;
 
-    pub fn flag_map_image(&self, game: &Game, tile_image_size: ImageDimensions) -> Image<Rgb8> {
-        let mut image = Image::new_uniform(Rgb8::BLACK, self.image_dimensions(tile_image_size));
-        let mut power_image_position_list = vec![
-            (self.properties.shape.max_axis_dimension(), ImageDimensions::new(0, 0));
-            game.powers.len()
-        ];
+        for y in 0..height {
+            for x in 0..width {
+                let tile = self.tiles.index(self.properties.shape.raw_index(x, y) as u32);
+                column_heights[x as usize] = if tile.main.owner == power_index { column_heights[x as usize] + 1 } else { 0 };
+            }
+
+            // Largest rectangle under the histogram of owned columns ending in this row.
+            let mut column_stack: Vec<usize> = vec![];
+            for x in 0..=width as usize {
+                let column_height = column_heights.get(x).copied().unwrap_or(0);
+
+                while let Some(&top) = column_stack.last() {
+                    if column_heights[top] < column_height {
+                        break;
+                    }
+                    column_stack.pop();
+
+                    let rectangle_height = column_heights[top];
+                    let left = column_stack.last().map_or(0, |&left| left + 1);
+                    let size = ImageDimensions::new(x - left, rectangle_height);
+                    let offset = ImageDimensions::new(left, y as usize + 1 - rectangle_height);
+
+                    if size.len() > 0 && largest.is_none_or(|(_, largest_size)| size.len() > largest_size.len()) {
+                        largest = Some((offset, size));
+                    }
+                }
+
+                column_stack.push(x);
+            }
+        }
+
+        largest
+    }
+
+
+
+    /// One pixel per tile, black at `min_height`, white at `max_height`, north at the top.
+    /// Same scale as `HeightmapImage`, so exported heights can be imported back.
+    pub fn height_image(&self, min_height: TerrainHeight, max_height: TerrainHeight) -> Image<Grey16> {
+        let height_range = ((max_height - min_height).to_meters() as i64).max(1);
+        let mut image = Image::new_uniform(Grey16::BLACK, self.properties.shape.max_axis_dimension());
 
         for (tile_index, tile) in self.tiles.into_iter().enumerate() {
-            let tile_position = ImageDimensions::from_u32_tuple(self.properties.shape.coordinates(tile_index));
+            let level = ((tile.main.height - min_height).to_meters() as i64 * u16::MAX as i64 / height_range)
+                .clamp(0, u16::MAX as i64);
 
-            power_image_position_list[tile.main.owner].0.x = power_image_position_list[tile.main.owner].0.x.min(tile_position.x);
-            power_image_position_list[tile.main.owner].0.y = power_image_position_list[tile.main.owner].0.y.min(tile_position.y);
-            power_image_position_list[tile.main.owner].1.x = power_image_position_list[tile.main.owner].1.x.max(tile_position.x);
-            power_image_position_list[tile.main.owner].1.y = power_image_position_list[tile.main.owner].1.y.max(tile_position.y);
+            image.set_pixel(self.tile_image_position(tile_index), Grey16::new(level as u16));
+        }
+
+        image.invert_on_y();
+        image
+    }
+
+    /// One pixel per tile in the color of its owner, north at the top.
+    pub fn owner_image(&self, powers: &[Power]) -> Image<Rgb8> {
+        let mut image = Image::new_uniform(Rgb8::BLACK, self.properties.shape.max_axis_dimension());
+
+        for (tile_index, tile) in self.tiles.into_iter().enumerate() {
+            let color = powers.get(tile.main.owner).map_or(Rgb8::BLACK, |power| power.color());
 
+            image.set_pixel(self.tile_image_position(tile_index), color);
         }
 
+        image.invert_on_y();
         image
     }
 
+    /// Lowest and highest tile.
+    pub fn height_range(&self) -> (TerrainHeight, TerrainHeight) {
+
+        self.tiles.into_iter().fold(
+            (TerrainHeight::from_meters(i32::MAX), TerrainHeight::from_meters(i32::MIN)),
+            |(min_height, max_height), tile| (
+                TerrainHeight::from_meters(min_height.to_meters().min(tile.main.height.to_meters())),
+                TerrainHeight::from_meters(max_height.to_meters().max(tile.main.height.to_meters())),
+            ),
+        )
+    }
+
+    /// Writes `height.png`, scaled over `height_range`, `owners.png`, `political.png` and `surface.png` into the directory.
+    pub fn export_images(&self, directory: &Path, powers: &[Power]) -> CoreResult<()> {
+        let (min_height, max_height) = self.height_range();
+        save_png_file(&self.height_image(min_height, max_height), &directory.join("height.png"))?;
+        save_png_file(&self.owner_image(powers), &directory.join("owners.png"))?;
+
+        let mut political_image = self.political_map_image(powers, ImageDimensions::new(16, 16))?;
+        political_image.invert_on_y();
+        save_png_file(&political_image, &directory.join("political.png"))?;
+
+        let mut surface_image = self.definitions.surface_types.build_surface_texture(self)?;
+        surface_image.invert_on_y();
+        save_png_file(&surface_image, &directory.join("surface.png"))
+    }
+
+    fn tile_image_position(&self, tile_index: usize) -> ImageDimensions {
+
+        ImageDimensions::from_u32_tuple(self.properties.shape.coordinates(tile_index).to_tuple())
+    }
+
 
 
     pub fn image_dimensions(&self, tile_image_dimensions: ImageDimensions) -> ImageDimensions {
@@ -117,18 +319,222 @@ impl Map {
         tile_image_dimensions * ImageDimensions::from_u32_tuple(self.properties.shape.max_axis())
     }
 
-    pub fn get_terrain(&self) -> (&MapSettings, &TileArray) {
+    pub fn tile(&self, coordinates: TileCoordinates) -> Option<TileLink<'_>> {
+
+        self.tiles.get_at(coordinates)
+    }
+
+    /// Owners can only be changed by `transfer_tiles`, which keeps borders and history up to date,
+    /// so tiles are changed in place one field at a time, marking their chunk dirty.
+    pub fn height_mut(&mut self, coordinates: TileCoordinates) -> Option<&mut TerrainHeight> {
+
+        self.tiles.get_at_mut(coordinates).map(|tile| &mut tile.main.height)
+    }
+
+    pub fn water_mut(&mut self, coordinates: TileCoordinates) -> Option<&mut TileWater> {
+
+        self.tiles.get_at_mut(coordinates).map(|tile| &mut tile.main.water)
+    }
+
+    pub fn climate_mut(&mut self, coordinates: TileCoordinates) -> Option<&mut TileClimate> {
+
+        self.tiles.get_at_mut(coordinates).map(|tile| &mut tile.main.climate)
+    }
+
+    /// Changes a single surface layer of the tile, checking it against definitions first.
+    pub fn set_surface(&mut self, coordinates: TileCoordinates, layer: usize, surface: TileSurface) -> CoreResult<()> {
+        let shape = self.properties.shape;
+        if !shape.contains(coordinates) {
+            return Err(CoreError::TileOutsideMap { x: coordinates.x, y: coordinates.y });
+        }
+        self.definitions.surface_types.check_surface(shape.index(coordinates), layer, &surface, self.properties.seed, coordinates)?;
+
+        if let Some(mut tile) = self.tiles.get_at_mut(coordinates) {
+            tile.set_surface(layer, surface);
+        }
+
+        Ok(())
+    }
+
+    /// Tiles sharing an edge with the given one, see `MapShape::adjacent`.
+    pub fn neighbours(&self, coordinates: TileCoordinates) -> impl Iterator<Item = (TileCoordinates, TileLink<'_>)> {
+
+        self.tiles.neighbours(coordinates)
+    }
+
+    /// Gives tiles to `new_owner`, skipping ones it already owns, or outside of the map.
+    /// Returns how many tiles changed their owner, transfers changing none aren't kept in the history.
+    /// History is kept in order of dates, so transfers dated before the last one fail.
+    /// Owners beyond `power_amount` fail too, without changing any tile.
+    pub fn transfer_tiles(&mut self, tiles: impl IntoIterator<Item = TileCoordinates>, new_owner: usize, reason: TransferReason, date: GameDate, power_amount: usize) -> CoreResult<usize> {
+        if let Some(last_transfer) = self.ownership_history.last() {
+            if date < last_transfer.date {
+                return Err(CoreError::TransferBeforeLastOne { date, last_date: last_transfer.date });
+            }
+        }
+        let shape = self.properties.shape;
+        let tiles: Vec<TileCoordinates> = tiles.into_iter()
+            .filter(|&coordinates| shape.contains(coordinates))
+            .collect();
+        if new_owner >= power_amount {
+            if let Some(&coordinates) = tiles.first() {
+                return Err(CoreError::UnknownOwner { tile_index: shape.index(coordinates), owner: new_owner });
+            }
+        }
+        let mut transferred = vec![];
+
+        for coordinates in tiles {
+            let Some(tile) = self.tiles.main_mut(shape.index(coordinates) as u32) else { continue };
+            if tile.owner == new_owner {
+                continue;
+            }
+
+            transferred.push((coordinates, tile.owner));
+            tile.owner = new_owner;
+            self.borders.update_tile(&self.tiles, coordinates);
+        }
+
+        let transferred_amount = transferred.len();
+        if transferred_amount > 0 {
+            self.ownership_history.push(OwnershipTransfer {
+                date,
+                reason,
+                new_owner,
+                tiles: transferred,
+            });
+        }
+
+        Ok(transferred_amount)
+    }
+
+    /// See `transfer_tiles`.
+    pub fn transfer_province(&mut self, province: usize, new_owner: usize, reason: TransferReason, date: GameDate, power_amount: usize) -> CoreResult<usize> {
+        let tiles: Vec<TileCoordinates> = self.provinces.province_tiles(province).collect();
+
+        self.transfer_tiles(tiles, new_owner, reason, date, power_amount)
+    }
+
+    /// See `transfer_tiles`.
+    pub fn transfer_region(&mut self, region: usize, new_owner: usize, reason: TransferReason, date: GameDate, power_amount: usize) -> CoreResult<usize> {
+        let tiles: Vec<TileCoordinates> = self.provinces.region_tiles(region).collect();
+
+        self.transfer_tiles(tiles, new_owner, reason, date, power_amount)
+    }
+
+    /// Oldest transfers first.
+    pub fn ownership_history(&self) -> &[OwnershipTransfer] {
+
+        &self.ownership_history
+    }
+
+    /// Transfers of the given tile, oldest first.
+    pub fn tile_history(&self, coordinates: TileCoordinates) -> impl Iterator<Item = &OwnershipTransfer> {
+
+        self.ownership_history.iter().filter(move |transfer| transfer.previous_owner(coordinates).is_some())
+    }
+
+    /// Owner the tile had at the given date, going back through the history, which is sorted by dates.
+    pub fn owner_at(&self, coordinates: TileCoordinates, date: GameDate) -> Option<usize> {
+        let mut owner = self.tile(coordinates)?.main.owner;
+
+        for transfer in self.ownership_history.iter().rev().take_while(|transfer| transfer.date > date) {
+            if let Some(previous_owner) = transfer.previous_owner(coordinates) {
+                owner = previous_owner;
+            }
+        }
+
+        Some(owner)
+    }
+
+    pub fn borders(&self) -> &BorderEdges {
+
+        &self.borders
+    }
+
+    /// Power owning most of the province's tiles, lower index winning ties, `None` for provinces with no tiles.
+    pub fn province_owner(&self, province: usize) -> Option<usize> {
+
+        self.majority_owner(self.provinces.province_tile_indices(province).iter().copied())
+    }
+
+    /// Power owning most of the region's tiles, see `province_owner`.
+    pub fn region_owner(&self, region: usize) -> Option<usize> {
+        let provinces = &self.provinces;
+
+        self.majority_owner(provinces.region_provinces(region).flat_map(|province| provinces.province_tile_indices(province).iter().copied()))
+    }
+
+    fn majority_owner(&self, tile_indices: impl Iterator<Item = usize>) -> Option<usize> {
+        let mut owner_counts: Vec<usize> = vec![];
+
+        for tile_index in tile_indices {
+            let owner = self.tiles.index(tile_index as u32).main.owner;
+            if owner >= owner_counts.len() {
+                owner_counts.resize(owner + 1, 0);
+            }
+
+            owner_counts[owner] += 1;
+        }
+
+        owner_counts.iter().enumerate()
+            .filter(|(_, &count)| count > 0)
+            .max_by(|(first_owner, first_count), (second_owner, second_count)| first_count.cmp(second_count).then(second_owner.cmp(first_owner)))
+            .map(|(owner, _)| owner)
+    }
+
+    pub fn get_terrain(&self) -> (&MapSettings, &TileChunks) {
 
         (&self.properties, &self.tiles)
     }
 
+
+
+    /// See `TileChunk::write`.
+    pub fn write_chunk(&self, chunk: ChunkCoordinates, writer: &mut impl Write) -> CoreResult<()> {
+
+        self.tiles.write_chunk(chunk, writer)
+    }
+
+    /// Replaces tiles of a chunk written by `write_chunk`, with borders following the loaded owners.
+    /// Chunks with owners beyond `power_amount`, or surfaces unknown to definitions, are rejected whole.
+    /// Ownership history is left as it is, as chunks don't carry it.
+    pub fn read_chunk(&mut self, reader: &mut impl Read, power_amount: usize) -> CoreResult<ChunkCoordinates> {
+        let shape = self.properties.shape;
+        let seed = self.properties.seed;
+        let surface_types = &self.definitions.surface_types;
+
+        let chunk = self.tiles.read_chunk(reader, |coordinates, tile| {
+            let tile_index = shape.index(coordinates);
+            if tile.main.owner >= power_amount {
+                return Err(CoreError::UnknownOwner { tile_index, owner: tile.main.owner });
+            }
+
+            tile.surface.iter().enumerate()
+                .try_for_each(|(layer_index, surface)| surface_types.check_surface(tile_index, layer_index, surface, seed, coordinates))
+        })?;
+
+        if let Some(loaded_chunk) = self.tiles.chunk(chunk) {
+            for (coordinates, _) in loaded_chunk.tiles() {
+                self.borders.update_tile(&self.tiles, coordinates);
+            }
+        }
+
+        Ok(chunk)
+    }
+
+    /// Chunks with tiles changed since the last call, or since the map was made.
+    pub fn take_dirty_chunks(&mut self) -> Vec<ChunkCoordinates> {
+
+        self.tiles.take_dirty_chunks()
+    }
+
 }
 
 
 
 /*pub struct SectorTiles {
 
-    last_rewards: Time,
+    last_rewards: GameDate,
 
     tiles: Vec<TileSector>,
 
@@ -136,7 +542,7 @@ impl Map {
 
 impl SectorTiles {
 
-    pub fn rewards_condition(&mut self, definitions: &Arc<Definitions>, sector_type_index: usize, current_time: Time) -> bool {
+    pub fn rewards_condition(&mut self, definitions: &Arc<Definitions>, sector_type_index: usize, current_time: GameDate) -> bool {
         let sector_type = &definitions.tile_sector_types[sector_type_index];
         let is_getting_rewards = definitions.tile_sector_types[sector_type_index].is_getting_rewards(self.last_rewards, current_time);
 
@@ -151,12 +557,23 @@ impl SectorTiles {
 
 
 
+#[derive(Clone)]
 pub struct MapSettings {
 
     pub tile_size: TerrainHeight,
 
     pub shape: MapShape,
 
+    /// Base of everything random about the map, the same seed always gives the same map.
+    pub seed: u64,
+    /// Terrain is generated only if it's set.
+    pub generator: Option<GeneratorSettings>,
+
+    /// Tiles below it are sea.
+    pub sea_level: TerrainHeight,
+    pub hydrology: HydrologySettings,
+    pub climate: ClimateSettings,
+
 }
 
 impl MapSettings {
@@ -166,7 +583,46 @@ impl MapSettings {
         Self {
             tile_size,
             shape,
+
+            seed: 0,
+            generator: None,
+
+            sea_level: TerrainHeight::from_meters(0),
+            hydrology: HydrologySettings::new(),
+            climate: ClimateSettings::new(),
+        }
+    }
+
+    /// `seed`, `[generator]`, `sea_level`, `[hydrology]` and `[climate]` are optional.
+    pub fn from_data(entry: &DataEntry, definitions: &Definitions) -> CoreResult<Self> {
+        let mut settings = Self::new(
+            TerrainHeight::from_meters(entry.get("tile_size")?.as_i32()?),
+            MapShape::from_data(entry)?,
+        );
+
+        if let Some(seed_entry) = entry.get_optional("seed")? {
+            settings.seed = seed_entry.as_u64()?;
+        }
+        if let Some(generator_entry) = entry.get_optional("generator")? {
+            settings.generator = Some(GeneratorSettings::from_data(&generator_entry, definitions)?);
+        }
+        if let Some(sea_level_entry) = entry.get_optional("sea_level")? {
+            settings.sea_level = TerrainHeight::from_meters(sea_level_entry.as_i32()?);
+        }
+        if let Some(hydrology_entry) = entry.get_optional("hydrology")? {
+            settings.hydrology = HydrologySettings::from_data(&hydrology_entry)?;
         }
+        if let Some(climate_entry) = entry.get_optional("climate")? {
+            settings.climate = ClimateSettings::from_data(&climate_entry)?;
+        }
+
+        Ok(settings)
+    }
+
+    /// Derives water, then climate, from final tile heights.
+    pub fn apply_environment(&self, tiles: &mut [TileLocal]) {
+        self.hydrology.apply(self, tiles);
+        self.climate.apply(self, tiles);
     }
 
 }
@@ -176,40 +632,203 @@ impl MapSettings {
 #[derive(Clone, Copy)]
 pub enum MapShape {
 
-    Rectangular { width: u32, height: u32 }
+    Rectangular { width: u32, height: u32 },
+    /// Pointy topped hexagons in rows, with every odd row shifted half a tile towards positive x.
+    Hexagonal { width: u32, height: u32 },
+    /// Rectangular tiles, with the x coordinate wrapping around, so the west edge meets the east one.
+    Cylindrical { width: u32, height: u32 },
 
 }
 
 impl MapShape {
 
+    /// Distance between rows of hexagons, which are one unit wide.
+    pub const HEXAGON_ROW_HEIGHT: f32 = 0.866_025_4;
+
+    pub fn from_data(entry: &DataEntry) -> CoreResult<Self> {
+        let shape_entry = entry.get("shape")?;
+        let read_dimension = |key: &str| -> CoreResult<u32> {
+            let dimension_entry = entry.get(key)?;
+            let dimension = dimension_entry.as_u32()?;
+            // Maps without tiles have nothing to index, or to find a range of heights in.
+            if dimension == 0 {
+                return Err(dimension_entry.error("must be above 0"));
+            }
+
+            Ok(dimension)
+        };
+        let width = read_dimension("width")?;
+        let height = read_dimension("height")?;
+
+        match shape_entry.as_str()? {
+            "rectangular" => Ok(MapShape::Rectangular { width, height }),
+            "hexagonal" => Ok(MapShape::Hexagonal { width, height }),
+            "cylindrical" => Ok(MapShape::Cylindrical { width, height }),
+            _ => Err(shape_entry.error("is not a known map shape")),
+        }
+    }
+
+
+
     pub fn raw_index(&self, x: u32, y: u32) -> usize {
 
         match self {
-            MapShape::Rectangular { width, .. } => (y * width + x) as usize,
+            MapShape::Rectangular { width, .. }
+            | MapShape::Hexagonal { width, .. }
+            | MapShape::Cylindrical { width, .. } => (y * width + x) as usize,
         }
     }
 
-    pub fn coordinates(&self, index: usize) -> (u32, u32) {
+    pub fn index(&self, coordinates: TileCoordinates) -> usize {
+
+        self.raw_index(coordinates.x, coordinates.y)
+    }
+
+    pub fn coordinates(&self, index: usize) -> TileCoordinates {
         let u32_index = index as u32;
 
         match self {
-            MapShape::Rectangular { width, .. } => (u32_index % width, u32_index / width),
+            MapShape::Rectangular { width, .. }
+            | MapShape::Hexagonal { width, .. }
+            | MapShape::Cylindrical { width, .. } => TileCoordinates::new(u32_index % width, u32_index / width),
         }
     }
 
+    pub fn contains(&self, coordinates: TileCoordinates) -> bool {
+        let (width, height) = self.max_axis();
+
+        coordinates.x < width && coordinates.y < height
+    }
+
+    /// Center of the tile, in tile widths from the map corner.
+    pub fn tile_center(&self, coordinates: TileCoordinates) -> (f32, f32) {
+        let TileCoordinates { x, y } = coordinates;
+
+        match self {
+            MapShape::Rectangular { .. }
+            | MapShape::Cylindrical { .. } => (x as f32 + 0.5, y as f32 + 0.5),
+            MapShape::Hexagonal { .. } => (
+                x as f32 + 0.5 + (y % 2) as f32 * 0.5,
+                (y as f32 + 0.5) * Self::HEXAGON_ROW_HEIGHT,
+            ),
+        }
+    }
+
+
+
+    /// Tile reached by moving from the given one, `None` past the map edge.
+    /// Wrapping shapes bring x coordinates from past one edge back from the other.
+    pub fn offset(&self, coordinates: TileCoordinates, x_shift: i64, y_shift: i64) -> Option<TileCoordinates> {
+        let (width, height) = self.max_axis();
+        let mut new_x = coordinates.x as i64 + x_shift;
+        let new_y = coordinates.y as i64 + y_shift;
+
+        if self.wraps_x() {
+            new_x = new_x.rem_euclid(width as i64);
+        }
+
+        if new_x < 0 || new_y < 0 || new_x >= width as i64 || new_y >= height as i64 {
+            return None;
+        }
+
+        Some(TileCoordinates::new(new_x as u32, new_y as u32))
+    }
+
+    /// Neighbour on a square grid, hexagonal maps should use `hex_neighbour`.
+    pub fn neighbour(&self, coordinates: TileCoordinates, direction: Direction) -> Option<TileCoordinates> {
+        let (x_shift, y_shift) = direction.shift();
+
+        self.offset(coordinates, x_shift, y_shift)
+    }
+
+    pub fn hex_neighbour(&self, coordinates: TileCoordinates, direction: HexDirection) -> Option<TileCoordinates> {
+        let (x_shift, y_shift) = direction.shift(coordinates.y);
+
+        self.offset(coordinates, x_shift, y_shift)
+    }
+
+    /// Tiles sharing an edge on a square grid, skipping ones past the map edge.
+    pub fn neighbours4(&self, coordinates: TileCoordinates) -> impl Iterator<Item = TileCoordinates> {
+        let shape = *self;
+
+        Direction::ORTHOGONAL.into_iter().filter_map(move |direction| shape.neighbour(coordinates, direction))
+    }
+
+    /// Tiles sharing an edge or a corner on a square grid, skipping ones past the map edge.
+    pub fn neighbours8(&self, coordinates: TileCoordinates) -> impl Iterator<Item = TileCoordinates> {
+        let shape = *self;
+
+        Direction::ALL.into_iter().filter_map(move |direction| shape.neighbour(coordinates, direction))
+    }
+
+    pub fn hex_neighbours(&self, coordinates: TileCoordinates) -> impl Iterator<Item = TileCoordinates> {
+        let shape = *self;
+
+        HexDirection::ALL.into_iter().filter_map(move |direction| shape.hex_neighbour(coordinates, direction))
+    }
+
+    /// Tiles sharing an edge with the given one, whatever the shape is.
+    pub fn adjacent(&self, coordinates: TileCoordinates) -> Box<dyn Iterator<Item = TileCoordinates>> {
+
+        match self {
+            MapShape::Rectangular { .. }
+            | MapShape::Cylindrical { .. } => Box::new(self.neighbours4(coordinates)),
+            MapShape::Hexagonal { .. } => Box::new(self.hex_neighbours(coordinates)),
+        }
+    }
+
+    /// Side of the tile's square on images facing the adjacent tile, as signs of x and y, with y growing north.
+    /// Hexagonal neighbours in other rows face either half of the top, or the bottom side, so they have both signs.
+    pub fn image_side(&self, coordinates: TileCoordinates, neighbour: TileCoordinates) -> (i32, i32) {
+        let mut x_shift = self.tile_center(neighbour).0 - self.tile_center(coordinates).0;
+        // Neighbours across the seam of wrapping maps lie on the other side.
+        if x_shift.abs() > 1.0 {
+            x_shift = -x_shift;
+        }
+        let x_side = if x_shift > 0.0 { 1 } else if x_shift < 0.0 { -1 } else { 0 };
+
+        (x_side, (neighbour.y as i64 - coordinates.y as i64).signum() as i32)
+    }
+
+    /// Straight line distance between tile centers, in tile widths, going across the seam when it's shorter.
+    pub fn distance(&self, from: TileCoordinates, to: TileCoordinates) -> f32 {
+        let from_center = self.tile_center(from);
+        let to_center = self.tile_center(to);
+
+        let mut x_distance = (to_center.0 - from_center.0).abs();
+        let y_distance = (to_center.1 - from_center.1).abs();
+
+        if self.wraps_x() {
+            let (display_width, _) = self.display_size();
+            x_distance = x_distance.min(display_width - x_distance);
+        }
+
+        (x_distance * x_distance + y_distance * y_distance).sqrt()
+    }
+
+    pub fn wraps_x(&self) -> bool {
+
+        matches!(self, MapShape::Cylindrical { .. })
+    }
+
 
 
     pub fn tile_amount(&self) -> u32 {
 
         match self {
-            MapShape::Rectangular { width, height } => width * height,
+            MapShape::Rectangular { width, height }
+            | MapShape::Hexagonal { width, height }
+            | MapShape::Cylindrical { width, height } => width * height,
         }
     }
 
+    /// Number of tiles along each axis, not the displayed size.
     pub fn max_axis(&self) -> (u32, u32) {
 
         match self {
-            MapShape::Rectangular { width, height } => (*width, *height),
+            MapShape::Rectangular { width, height }
+            | MapShape::Hexagonal { width, height }
+            | MapShape::Cylindrical { width, height } => (*width, *height),
         }
     }
 
@@ -218,4 +837,24 @@ impl MapShape {
         ImageDimensions::from_u32_tuple(self.max_axis())
     }
 
+    /// Size of the whole map, as it's displayed, in tile widths.
+    pub fn display_size(&self) -> (f32, f32) {
+
+        match self {
+            MapShape::Rectangular { width, height }
+            | MapShape::Cylindrical { width, height } => (*width as f32, *height as f32),
+            MapShape::Hexagonal { width, height } => (*width as f32 + 0.5, *height as f32 * Self::HEXAGON_ROW_HEIGHT),
+        }
+    }
+
+}
+
+
+
+fn save_png_file<Color: ColorFn + PartialEq + Clone + Copy + From<Rgb8> + From<Rgba8>>(image: &Image<Color>, path: &Path) -> CoreResult<()>
+    where [(); Color::BYTE_LENGTH]: Sized
+{
+    let writer = BufWriter::new(File::create(path).to_core_error()?);
+
+    image.save_png(writer).to_core_error()
 }
@@ -0,0 +1,275 @@
+use std::collections::BTreeSet;
+use crate::map::MapShape;
+use crate::map::coordinates::TileCoordinates;
+use crate::map::tile::chunks::TileChunks;
+use crate::units::GameDate;
+
+
+
+/// Why tiles changed their owner.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TransferReason {
+
+    War,
+    Treaty,
+    Purchase,
+    Rebellion,
+    Other(String),
+
+}
+
+
+
+/// Single call of `Map::transfer_tiles`, with every tile, that really changed its owner.
+#[derive(Clone, Debug)]
+pub struct OwnershipTransfer {
+
+    pub date: GameDate,
+    pub reason: TransferReason,
+    pub new_owner: usize,
+    /// Tiles with their owners from before the transfer.
+    pub tiles: Vec<(TileCoordinates, usize)>,
+
+}
+
+impl OwnershipTransfer {
+
+    /// Owner the tile had before the transfer, `None` if it wasn't transferred.
+    pub fn previous_owner(&self, coordinates: TileCoordinates) -> Option<usize> {
+
+        self.tiles.iter()
+            .find(|(tile, _)| *tile == coordinates)
+            .map(|(_, previous_owner)| *previous_owner)
+    }
+
+}
+
+
+
+/// Shared edge of two neighbouring tiles with different owners, the tile with lower index being first.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct BorderEdge {
+
+    pub tile: TileCoordinates,
+    pub neighbour: TileCoordinates,
+
+}
+
+
+
+/// Every edge between tiles of different owners, kept up to date as tiles are transferred.
+#[derive(Clone)]
+pub struct BorderEdges {
+
+    shape: MapShape,
+    edges: BTreeSet<(usize, usize)>,
+    /// Grows with every owner change, so users can tell their copies are outdated, even if no edge changed.
+    revision: u64,
+
+}
+
+impl BorderEdges {
+
+    pub fn new(shape: MapShape, tiles: &TileChunks) -> Self {
+        let mut borders = Self {
+            shape,
+            edges: BTreeSet::new(),
+            revision: 0,
+        };
+
+        for tile_index in 0..shape.tile_amount() as usize {
+            borders.update_tile(tiles, shape.coordinates(tile_index));
+        }
+        borders.revision = 0;
+
+        borders
+    }
+
+    /// Rechecks edges around a single tile, after its owner changed.
+    pub fn update_tile(&mut self, tiles: &TileChunks, coordinates: TileCoordinates) {
+        let shape = self.shape;
+        let tile_index = shape.index(coordinates);
+        let owner = tiles.index(tile_index as u32).main.owner;
+
+        for (neighbour, neighbour_tile) in tiles.neighbours(coordinates) {
+            let neighbour_index = shape.index(neighbour);
+            let edge = (tile_index.min(neighbour_index), tile_index.max(neighbour_index));
+
+            if neighbour_tile.main.owner != owner {
+                self.edges.insert(edge);
+            } else {
+                self.edges.remove(&edge);
+            }
+        }
+
+        self.revision += 1;
+    }
+
+
+
+    pub fn contains(&self, tile: TileCoordinates, neighbour: TileCoordinates) -> bool {
+        let tile_index = self.shape.index(tile);
+        let neighbour_index = self.shape.index(neighbour);
+
+        self.edges.contains(&(tile_index.min(neighbour_index), tile_index.max(neighbour_index)))
+    }
+
+    /// Sorted by the indices of their tiles.
+    pub fn iter(&self) -> impl Iterator<Item = BorderEdge> + '_ {
+
+        self.edges.iter().map(|&(tile_index, neighbour_index)| BorderEdge {
+            tile: self.shape.coordinates(tile_index),
+            neighbour: self.shape.coordinates(neighbour_index),
+        })
+    }
+
+    /// Edges with the given tile on either side.
+    pub fn tile_edges(&self, coordinates: TileCoordinates) -> impl Iterator<Item = BorderEdge> + '_ {
+
+        self.shape.adjacent(coordinates)
+            .filter(move |&neighbour| self.contains(coordinates, neighbour))
+            .map(move |neighbour| if self.shape.index(coordinates) < self.shape.index(neighbour) {
+                BorderEdge { tile: coordinates, neighbour }
+            } else {
+                BorderEdge { tile: neighbour, neighbour: coordinates }
+            })
+    }
+
+    pub fn len(&self) -> usize {
+
+        self.edges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+
+        self.edges.is_empty()
+    }
+
+    pub fn revision(&self) -> u64 {
+
+        self.revision
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+    use std::sync::Arc;
+    use crate::Definitions;
+    use crate::error::CoreError;
+    use crate::map::{Map, MapSettings};
+    use crate::map::tile::TileLocal;
+    use crate::map::tile::surface::TileSurface;
+    use crate::map::units::TerrainHeight;
+    use crate::units::TimeSpan;
+    use super::*;
+
+    const POWER_AMOUNT: usize = 3;
+
+    fn test_map(shape: MapShape) -> Map {
+        let definitions = Arc::new(Definitions::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../game sets/historical")).unwrap());
+        let filler = TileLocal::new(0, vec![TileSurface::new_automatic(0), TileSurface::EMPTY].into_boxed_slice());
+
+        Map::new(definitions, MapSettings::new(TerrainHeight::from_meters(1000), shape), filler)
+    }
+
+    fn day(days: i64) -> GameDate {
+
+        GameDate::EPOCH + TimeSpan::from_days(days)
+    }
+
+    fn assert_borders_rebuilt(map: &Map) {
+        let (properties, tiles) = map.get_terrain();
+        let rebuilt = BorderEdges::new(properties.shape, tiles);
+
+        assert_eq!(map.borders().iter().collect::<Vec<_>>(), rebuilt.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn history_is_kept_in_order() {
+        let mut map = test_map(MapShape::Rectangular { width: 4, height: 3 });
+        let tile = TileCoordinates::new(1, 1);
+
+        assert_eq!(map.transfer_tiles([tile, TileCoordinates::new(2, 1)], 1, TransferReason::War, day(10), POWER_AMOUNT).unwrap(), 2);
+        assert_eq!(map.transfer_tiles([tile], 2, TransferReason::Treaty, day(20), POWER_AMOUNT).unwrap(), 1);
+        // Same date as the last transfer is still in order.
+        assert_eq!(map.transfer_tiles([TileCoordinates::new(0, 0)], 2, TransferReason::Purchase, day(20), POWER_AMOUNT).unwrap(), 1);
+
+        let history = map.ownership_history();
+        assert_eq!(history.iter().map(|transfer| (transfer.date, transfer.new_owner)).collect::<Vec<_>>(), vec![(day(10), 1), (day(20), 2), (day(20), 2)]);
+        assert_eq!(history[1].previous_owner(tile), Some(1));
+        assert_eq!(history[1].previous_owner(TileCoordinates::new(2, 1)), None);
+        assert_eq!(map.tile_history(tile).count(), 2);
+
+        assert_eq!(map.owner_at(tile, day(5)), Some(0));
+        assert_eq!(map.owner_at(tile, day(15)), Some(1));
+        assert_eq!(map.owner_at(tile, day(25)), Some(2));
+        assert_eq!(map.owner_at(TileCoordinates::new(4, 0), day(25)), None);
+    }
+
+    #[test]
+    fn transfers_before_the_last_one_are_rejected() {
+        let mut map = test_map(MapShape::Rectangular { width: 4, height: 3 });
+        let tile = TileCoordinates::new(1, 1);
+        map.transfer_tiles([tile], 1, TransferReason::War, day(20), POWER_AMOUNT).unwrap();
+
+        let result = map.transfer_tiles([tile], 2, TransferReason::Treaty, day(19), POWER_AMOUNT);
+        assert!(matches!(result, Err(CoreError::TransferBeforeLastOne { date, last_date }) if date == day(19) && last_date == day(20)));
+        assert_eq!(map.tile(tile).unwrap().main.owner, 1);
+        assert_eq!(map.ownership_history().len(), 1);
+    }
+
+    #[test]
+    fn unknown_owners_are_rejected() {
+        let mut map = test_map(MapShape::Rectangular { width: 4, height: 3 });
+        let revision = map.borders().revision();
+
+        let result = map.transfer_tiles([TileCoordinates::new(2, 2), TileCoordinates::new(3, 2)], POWER_AMOUNT, TransferReason::War, day(1), POWER_AMOUNT);
+        assert!(matches!(result, Err(CoreError::UnknownOwner { tile_index: 10, owner: POWER_AMOUNT })));
+        assert!(map.get_terrain().1.iter().all(|tile| tile.main.owner == 0));
+        assert!(map.ownership_history().is_empty());
+        assert_eq!(map.borders().revision(), revision);
+    }
+
+    #[test]
+    fn no_op_transfers_are_left_out_of_history() {
+        let mut map = test_map(MapShape::Rectangular { width: 4, height: 3 });
+
+        assert_eq!(map.transfer_tiles([TileCoordinates::new(0, 0), TileCoordinates::new(7, 7)], 0, TransferReason::Other("nothing".into()), day(1), POWER_AMOUNT).unwrap(), 0);
+        assert_eq!(map.transfer_tiles(Vec::new(), 1, TransferReason::War, day(2), POWER_AMOUNT).unwrap(), 0);
+        assert!(map.ownership_history().is_empty());
+
+        map.transfer_tiles([TileCoordinates::new(0, 0)], 1, TransferReason::War, day(3), POWER_AMOUNT).unwrap();
+        assert_eq!(map.transfer_tiles([TileCoordinates::new(0, 0)], 1, TransferReason::War, day(4), POWER_AMOUNT).unwrap(), 0);
+        assert_eq!(map.ownership_history().len(), 1);
+    }
+
+    #[test]
+    fn borders_follow_transfers() {
+        for shape in [MapShape::Rectangular { width: 5, height: 4 }, MapShape::Hexagonal { width: 5, height: 4 }, MapShape::Cylindrical { width: 5, height: 4 }] {
+            let mut map = test_map(shape);
+            assert!(map.borders().is_empty());
+
+            let corner = TileCoordinates::new(0, 0);
+            map.transfer_tiles([corner], 1, TransferReason::War, day(1), POWER_AMOUNT).unwrap();
+            assert_eq!(map.borders().len(), shape.adjacent(corner).count());
+            assert_eq!(map.borders().tile_edges(corner).count(), shape.adjacent(corner).count());
+            assert_borders_rebuilt(&map);
+
+            let revision = map.borders().revision();
+            map.transfer_tiles([TileCoordinates::new(1, 0), TileCoordinates::new(2, 2), TileCoordinates::new(4, 3)], 2, TransferReason::War, day(2), POWER_AMOUNT).unwrap();
+            assert!(map.borders().revision() > revision);
+            assert!(map.borders().contains(corner, TileCoordinates::new(1, 0)));
+            assert_borders_rebuilt(&map);
+
+            // Giving everything back removes every edge again.
+            map.transfer_tiles((0..shape.tile_amount() as usize).map(|index| shape.coordinates(index)), 0, TransferReason::Treaty, day(3), POWER_AMOUNT).unwrap();
+            assert!(map.borders().is_empty());
+        }
+    }
+
+}
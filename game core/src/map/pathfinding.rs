@@ -0,0 +1,463 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use crate::map::{Map, MapShape};
+use crate::map::coordinates::TileCoordinates;
+use crate::map::tile::TileLink;
+
+
+
+/// Extra restriction on entering tiles, `false` meaning the tile can't be entered.
+pub type TileFilter = Box<dyn Fn(TileCoordinates, TileLink) -> bool>;
+
+/// Who is moving and how terrain slows them down.
+/// Costs are counted in moves across a single tile of plain, flat ground.
+pub struct MovementRules {
+
+    /// Added for every meter climbed up.
+    pub climb_cost_per_meter: f32,
+    /// Added for every meter descended.
+    pub descent_cost_per_meter: f32,
+    /// Added for stepping onto a river, unless already moving along one.
+    pub river_crossing_cost: f32,
+    /// Lets sea and lake tiles be entered, like by ships.
+    pub open_water_passable: bool,
+    /// Lets square tiles be crossed diagonally, hexagons always have just their 6 neighbours.
+    pub diagonal_moves: bool,
+
+    /// Powers, whose tiles can't be entered, like ones with closed borders.
+    pub closed_powers: Vec<usize>,
+    pub tile_filter: Option<TileFilter>,
+
+}
+
+impl MovementRules {
+
+    pub fn new() -> Self {
+
+        Self {
+            climb_cost_per_meter: 0.005,
+            descent_cost_per_meter: 0.001,
+            river_crossing_cost: 1.0,
+            open_water_passable: false,
+            diagonal_moves: true,
+
+            closed_powers: vec![],
+            tile_filter: None,
+        }
+    }
+
+    /// Default rules, with territory of the given powers closed.
+    pub fn with_closed_powers(closed_powers: Vec<usize>) -> Self {
+
+        Self {
+            closed_powers,
+            .. Self::new()
+        }
+    }
+
+}
+
+impl Default for MovementRules {
+    fn default() -> Self {
+
+        Self::new()
+    }
+}
+
+
+
+/// Tiles from start to goal, both included.
+#[derive(Clone, Debug)]
+pub struct TilePath {
+
+    pub tiles: Vec<TileCoordinates>,
+    pub cost: f32,
+
+}
+
+
+
+/// Cheapest costs of reaching tiles from a single start, within some budget.
+pub struct ReachabilityField {
+
+    shape: MapShape,
+    start: TileCoordinates,
+    costs: Vec<Option<f32>>,
+    previous: Vec<Option<usize>>,
+
+}
+
+impl ReachabilityField {
+
+    /// `None` for tiles out of the budget, or not reachable at all.
+    pub fn cost(&self, coordinates: TileCoordinates) -> Option<f32> {
+
+        if self.shape.contains(coordinates) {
+            self.costs[self.shape.index(coordinates)]
+        } else {
+            None
+        }
+    }
+
+    pub fn path_to(&self, goal: TileCoordinates) -> Option<TilePath> {
+        let cost = self.cost(goal)?;
+
+        Some(TilePath {
+            tiles: rebuild_path(self.shape, &self.previous, goal),
+            cost,
+        })
+    }
+
+    pub fn reachable_tiles(&self) -> impl Iterator<Item = (TileCoordinates, f32)> + '_ {
+
+        self.costs.iter().enumerate()
+            .filter_map(|(tile_index, cost)| cost.map(|cost| (self.shape.coordinates(tile_index), cost)))
+    }
+
+    pub fn start(&self) -> TileCoordinates {
+
+        self.start
+    }
+
+}
+
+
+
+/// A* and Dijkstra searches over the tiles of a map.
+pub struct Pathfinder<'a> {
+
+    map: &'a Map,
+    rules: MovementRules,
+    /// Cheapest cost of a single tile width, keeping A* estimates from overshooting.
+    min_step_cost: f32,
+
+}
+
+impl<'a> Pathfinder<'a> {
+
+    pub fn new(map: &'a Map, rules: MovementRules) -> Self {
+
+        Self {
+            min_step_cost: map.definitions.surface_types.min_movement_cost(),
+            map,
+            rules,
+        }
+    }
+
+
+
+    /// Cost of moving between neighbours, `None` if the target tile can't be entered.
+    pub fn step_cost(&self, from: TileCoordinates, to: TileCoordinates) -> Option<f32> {
+        let from_tile = self.map.tile(from)?;
+        let to_tile = self.map.tile(to)?;
+
+        if !self.can_enter(to, to_tile) {
+            return None;
+        }
+
+        let surface_cost = self.map.definitions.surface_types.movement_cost(to_tile.surface)?;
+        let distance = self.map.properties.shape.distance(from, to);
+        let height_difference = (to_tile.main.height - from_tile.main.height).to_meters() as f32;
+
+        let height_cost = if height_difference > 0.0 {
+            height_difference * self.rules.climb_cost_per_meter
+        } else {
+            -height_difference * self.rules.descent_cost_per_meter
+        };
+
+        let river_cost = if to_tile.water().is_river() && !from_tile.water().is_river() {
+            self.rules.river_crossing_cost
+        } else {
+            0.0
+        };
+
+        Some(distance * surface_cost + height_cost + river_cost)
+    }
+
+    /// Cheapest path with A*, `None` if the goal can't be reached.
+    pub fn find_path(&self, start: TileCoordinates, goal: TileCoordinates) -> Option<TilePath> {
+        let shape = self.map.properties.shape;
+        if !shape.contains(start) || !shape.contains(goal) {
+            return None;
+        }
+
+        let mut costs = vec![None; shape.tile_amount() as usize];
+        let mut previous = vec![None; shape.tile_amount() as usize];
+        let mut open = BinaryHeap::new();
+
+        costs[shape.index(start)] = Some(0.0);
+        open.push(OpenTile { priority: self.estimate(start, goal), cost: 0.0, tile_index: shape.index(start) });
+
+        while let Some(OpenTile { cost, tile_index, .. }) = open.pop() {
+            let coordinates = shape.coordinates(tile_index);
+
+            if coordinates == goal {
+                return Some(TilePath {
+                    tiles: rebuild_path(shape, &previous, goal),
+                    cost,
+                });
+            }
+            // Stale entry, the tile was already reached cheaper.
+            if costs[tile_index].is_some_and(|known_cost| cost > known_cost) {
+                continue;
+            }
+
+            for neighbour in self.neighbours(coordinates) {
+                let Some(step_cost) = self.step_cost(coordinates, neighbour) else { continue };
+                let neighbour_index = shape.index(neighbour);
+                let neighbour_cost = cost + step_cost;
+
+                if costs[neighbour_index].is_none_or(|known_cost| neighbour_cost < known_cost) {
+                    costs[neighbour_index] = Some(neighbour_cost);
+                    previous[neighbour_index] = Some(tile_index);
+                    open.push(OpenTile {
+                        priority: neighbour_cost + self.estimate(neighbour, goal),
+                        cost: neighbour_cost,
+                        tile_index: neighbour_index,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Every tile reachable within the budget, with Dijkstra.
+    pub fn reachable(&self, start: TileCoordinates, budget: f32) -> ReachabilityField {
+        let shape = self.map.properties.shape;
+        let mut field = ReachabilityField {
+            shape,
+            start,
+            costs: vec![None; shape.tile_amount() as usize],
+            previous: vec![None; shape.tile_amount() as usize],
+        };
+        if !shape.contains(start) {
+            return field;
+        }
+
+        let mut open = BinaryHeap::new();
+        field.costs[shape.index(start)] = Some(0.0);
+        open.push(OpenTile { priority: 0.0, cost: 0.0, tile_index: shape.index(start) });
+
+        while let Some(OpenTile { cost, tile_index, .. }) = open.pop() {
+            if field.costs[tile_index].is_some_and(|known_cost| cost > known_cost) {
+                continue;
+            }
+
+            let coordinates = shape.coordinates(tile_index);
+            for neighbour in self.neighbours(coordinates) {
+                let Some(step_cost) = self.step_cost(coordinates, neighbour) else { continue };
+                let neighbour_index = shape.index(neighbour);
+                let neighbour_cost = cost + step_cost;
+
+                if neighbour_cost <= budget && field.costs[neighbour_index].is_none_or(|known_cost| neighbour_cost < known_cost) {
+                    field.costs[neighbour_index] = Some(neighbour_cost);
+                    field.previous[neighbour_index] = Some(tile_index);
+                    open.push(OpenTile { priority: neighbour_cost, cost: neighbour_cost, tile_index: neighbour_index });
+                }
+            }
+        }
+
+        field
+    }
+
+
+
+    fn can_enter(&self, coordinates: TileCoordinates, tile: TileLink) -> bool {
+
+        (self.rules.open_water_passable || !tile.water().is_open_water())
+            && !self.rules.closed_powers.contains(&tile.main.owner)
+            && self.rules.tile_filter.as_ref().is_none_or(|tile_filter| tile_filter(coordinates, tile))
+    }
+
+    fn neighbours(&self, coordinates: TileCoordinates) -> Box<dyn Iterator<Item = TileCoordinates>> {
+        let shape = self.map.properties.shape;
+
+        match shape {
+            MapShape::Rectangular { .. }
+            | MapShape::Cylindrical { .. } if self.rules.diagonal_moves => Box::new(shape.neighbours8(coordinates)),
+            _ => shape.adjacent(coordinates),
+        }
+    }
+
+    fn estimate(&self, from: TileCoordinates, goal: TileCoordinates) -> f32 {
+
+        self.map.properties.shape.distance(from, goal) * self.min_step_cost
+    }
+
+}
+
+
+
+fn rebuild_path(shape: MapShape, previous: &[Option<usize>], goal: TileCoordinates) -> Vec<TileCoordinates> {
+    let mut tiles = vec![goal];
+    let mut tile_index = shape.index(goal);
+
+    while let Some(previous_index) = previous[tile_index] {
+        tiles.push(shape.coordinates(previous_index));
+        tile_index = previous_index;
+    }
+
+    tiles.reverse();
+    tiles
+}
+
+
+
+/// Entry of the open set, ordered so `BinaryHeap` pops the lowest priority first.
+struct OpenTile {
+
+    priority: f32,
+    cost: f32,
+    tile_index: usize,
+
+}
+
+impl PartialEq for OpenTile {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenTile {}
+
+impl PartialOrd for OpenTile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenTile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+            .then_with(|| other.tile_index.cmp(&self.tile_index))
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::f32::consts::SQRT_2;
+    use std::path::Path;
+    use std::sync::Arc;
+    use crate::Definitions;
+    use crate::map::MapSettings;
+    use crate::map::ownership::TransferReason;
+    use crate::map::tile::TileLocal;
+    use crate::map::tile::surface::TileSurface;
+    use crate::map::units::TerrainHeight;
+    use crate::units::GameDate;
+    use super::*;
+
+    const MOUNTAINS: usize = 2;
+
+    /// Flat plains, all owned by power 0.
+    fn test_map(shape: MapShape) -> Map {
+        let definitions = Arc::new(Definitions::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../game sets/historical")).unwrap());
+        let filler = TileLocal::new(0, vec![TileSurface::new(0, 0), TileSurface::EMPTY].into_boxed_slice());
+
+        Map::new(definitions, MapSettings::new(TerrainHeight::from_meters(1000), shape), filler)
+    }
+
+    fn tile(x: u32, y: u32) -> TileCoordinates {
+
+        TileCoordinates::new(x, y)
+    }
+
+    fn assert_cost(path: &TilePath, expected: f32) {
+        assert!((path.cost - expected).abs() < 1e-4, "cost {} instead of {expected}", path.cost);
+    }
+
+    #[test]
+    fn paths_are_cheapest() {
+        let mut map = test_map(MapShape::Rectangular { width: 5, height: 3 });
+        map.set_surface(tile(2, 0), 0, TileSurface::new(MOUNTAINS, 0)).unwrap();
+
+        // Going diagonally around the mountains is cheaper than crossing them.
+        let pathfinder = Pathfinder::new(&map, MovementRules::new());
+        let path = pathfinder.find_path(tile(0, 0), tile(4, 0)).unwrap();
+        assert_cost(&path, 2.0 + 2.0 * SQRT_2);
+        assert_eq!(path.tiles, vec![tile(0, 0), tile(1, 0), tile(2, 1), tile(3, 0), tile(4, 0)]);
+
+        // Without diagonals, crossing them costs as much as the way around, both 6.
+        let pathfinder = Pathfinder::new(&map, MovementRules { diagonal_moves: false, .. MovementRules::new() });
+        let path = pathfinder.find_path(tile(0, 0), tile(4, 0)).unwrap();
+        assert_cost(&path, 6.0);
+        assert_eq!(path.tiles.first(), Some(&tile(0, 0)));
+        assert_eq!(path.tiles.last(), Some(&tile(4, 0)));
+
+        let same_tile = pathfinder.find_path(tile(1, 1), tile(1, 1)).unwrap();
+        assert_eq!(same_tile.tiles, vec![tile(1, 1)]);
+        assert_cost(&same_tile, 0.0);
+    }
+
+    #[test]
+    fn reachability_matches_paths() {
+        let mut map = test_map(MapShape::Rectangular { width: 5, height: 3 });
+        map.set_surface(tile(2, 0), 0, TileSurface::new(MOUNTAINS, 0)).unwrap();
+        let pathfinder = Pathfinder::new(&map, MovementRules { diagonal_moves: false, .. MovementRules::new() });
+
+        let field = pathfinder.reachable(tile(0, 0), 3.0);
+        assert_eq!(field.cost(tile(1, 0)), Some(1.0));
+        assert_eq!(field.cost(tile(2, 1)), Some(3.0));
+        assert_eq!(field.cost(tile(2, 0)), None);
+        assert_eq!(field.cost(tile(4, 0)), None);
+
+        let unlimited = pathfinder.reachable(tile(0, 0), f32::INFINITY);
+        assert_eq!(unlimited.reachable_tiles().count(), 15);
+        for (coordinates, cost) in unlimited.reachable_tiles() {
+            assert_cost(&pathfinder.find_path(tile(0, 0), coordinates).unwrap(), cost);
+            assert_cost(&unlimited.path_to(coordinates).unwrap(), cost);
+        }
+    }
+
+    #[test]
+    fn paths_avoid_closed_powers() {
+        let mut map = test_map(MapShape::Rectangular { width: 5, height: 3 });
+        map.transfer_tiles([tile(2, 0), tile(2, 1)], 1, TransferReason::War, GameDate::EPOCH, 2).unwrap();
+
+        let open = Pathfinder::new(&map, MovementRules { diagonal_moves: false, .. MovementRules::new() });
+        assert_cost(&open.find_path(tile(0, 0), tile(4, 0)).unwrap(), 4.0);
+
+        let closed = Pathfinder::new(&map, MovementRules { diagonal_moves: false, .. MovementRules::with_closed_powers(vec![1]) });
+        let path = closed.find_path(tile(0, 0), tile(4, 0)).unwrap();
+        assert_cost(&path, 8.0);
+        assert!(path.tiles.iter().all(|&coordinates| map.tile(coordinates).unwrap().main.owner == 0));
+    }
+
+    #[test]
+    fn unreachable_goals_have_no_path() {
+        let mut map = test_map(MapShape::Rectangular { width: 5, height: 3 });
+        map.transfer_tiles([tile(2, 0), tile(2, 1), tile(2, 2)], 1, TransferReason::War, GameDate::EPOCH, 2).unwrap();
+
+        let closed = Pathfinder::new(&map, MovementRules::with_closed_powers(vec![1]));
+        assert!(closed.find_path(tile(0, 0), tile(4, 0)).is_none());
+        assert_eq!(closed.reachable(tile(0, 0), f32::INFINITY).reachable_tiles().count(), 6);
+        // Goals outside of the map can't be reached either.
+        assert!(closed.find_path(tile(0, 0), tile(5, 0)).is_none());
+
+        let filtered = Pathfinder::new(&map, MovementRules {
+            tile_filter: Some(Box::new(|coordinates, _| coordinates.x != 3)),
+            .. MovementRules::new()
+        });
+        assert!(filtered.find_path(tile(0, 0), tile(4, 0)).is_none());
+        assert!(filtered.find_path(tile(0, 0), tile(2, 2)).is_some());
+    }
+
+    #[test]
+    fn paths_cross_the_seam() {
+        let cylindrical = test_map(MapShape::Cylindrical { width: 8, height: 3 });
+        let pathfinder = Pathfinder::new(&cylindrical, MovementRules::new());
+        let path = pathfinder.find_path(tile(0, 1), tile(7, 1)).unwrap();
+        assert_eq!(path.tiles, vec![tile(0, 1), tile(7, 1)]);
+        assert_cost(&path, 1.0);
+        assert_cost(&pathfinder.find_path(tile(1, 0), tile(6, 0)).unwrap(), 3.0);
+
+        let rectangular = test_map(MapShape::Rectangular { width: 8, height: 3 });
+        let pathfinder = Pathfinder::new(&rectangular, MovementRules::new());
+        assert_cost(&pathfinder.find_path(tile(0, 1), tile(7, 1)).unwrap(), 7.0);
+    }
+
+}
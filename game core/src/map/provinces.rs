@@ -0,0 +1,430 @@
+use std::path::Path;
+use crate::data::DataEntry;
+use crate::error::CoreResult;
+use crate::image::ImageDimensions;
+use crate::image::color::Rgb8;
+use crate::map::{MapSettings, MapShape};
+use crate::map::coordinates::TileCoordinates;
+use crate::map::import::{check_dimensions, ColorKeyImage};
+use crate::population::Population;
+
+
+
+/// Named group of tiles, the unit of economy and diplomacy.
+#[derive(Clone)]
+pub struct Province {
+
+    pub name: String,
+    /// Always one of the province's own tiles.
+    pub capital: TileCoordinates,
+    pub region: Option<usize>,
+    pub population: Population,
+
+}
+
+
+
+/// Named group of provinces, like a state or a historical land.
+#[derive(Clone)]
+pub struct Region {
+
+    pub name: String,
+    /// Always a tile of one of the region's provinces.
+    pub capital: TileCoordinates,
+
+}
+
+
+
+/// Provinces and regions of a map, with lookups between them and tiles both ways.
+#[derive(Clone)]
+pub struct Provinces {
+
+    provinces: Vec<Province>,
+    regions: Vec<Region>,
+
+    shape: MapShape,
+    tile_provinces: Vec<Option<usize>>,
+    province_tiles: Vec<Vec<usize>>,
+    /// Provinces sharing an edge of any of their tiles, sorted by id.
+    adjacent_provinces: Vec<Vec<usize>>,
+
+}
+
+impl Provinces {
+
+    /// Every tile of `tile_provinces` is either in no province, or in one of `provinces`.
+    pub fn new(shape: MapShape, provinces: Vec<Province>, regions: Vec<Region>, tile_provinces: Vec<Option<usize>>) -> Self {
+        let mut province_tiles = vec![vec![]; provinces.len()];
+        let mut adjacent_provinces = vec![vec![]; provinces.len()];
+
+        for (tile_index, province) in tile_provinces.iter().enumerate() {
+            let Some(province) = *province else { continue };
+            province_tiles[province].push(tile_index);
+
+            for neighbour in shape.adjacent(shape.coordinates(tile_index)) {
+                if let Some(neighbour_province) = tile_provinces[shape.index(neighbour)] {
+                    if neighbour_province != province {
+                        adjacent_provinces[province].push(neighbour_province);
+                    }
+                }
+            }
+        }
+
+        for adjacent in &mut adjacent_provinces {
+            adjacent.sort_unstable();
+            adjacent.dedup();
+        }
+
+        Self {
+            provinces,
+            regions,
+
+            shape,
+            tile_provinces,
+            province_tiles,
+            adjacent_provinces,
+        }
+    }
+
+    /// No provinces, with every tile being in none.
+    pub fn new_empty(shape: MapShape) -> Self {
+
+        Self::new(shape, vec![], vec![], vec![None; shape.tile_amount() as usize])
+    }
+
+    /// Reads `region` and `province` arrays, with tiles painted in province colors on `image`, relative to `directory`.
+    /// Pixels of `empty_color` are in no province, any other color must belong to a province.
+    pub fn from_data(entry: &DataEntry, directory: &Path, map_settings: &MapSettings) -> CoreResult<Self> {
+        let shape = map_settings.shape;
+
+        let empty_color = match entry.get_optional("empty_color")? {
+            Some(color_entry) => Some(Self::color_from_data(&color_entry, color_entry.as_str()?)?),
+            None => None,
+        };
+
+        let mut regions: Vec<Region> = vec![];
+        let mut region_capital_entries = vec![];
+        for region_entry in entry.optional_array("region")? {
+            let capital_entry = region_entry.get("capital")?;
+            let name_entry = region_entry.get("name")?;
+            let name = name_entry.as_str()?;
+            if regions.iter().any(|region| region.name == name) {
+                return Err(name_entry.error(format!("region `{name}` is already defined")));
+            }
+
+            regions.push(Region {
+                name: name.to_string(),
+                capital: Self::coordinates_from_data(&capital_entry, shape)?,
+            });
+            region_capital_entries.push(capital_entry);
+        }
+
+        let mut provinces: Vec<Province> = vec![];
+        let mut province_capital_entries = vec![];
+        let mut key: Vec<(Rgb8, usize)> = vec![];
+        for province_entry in entry.optional_array("province")? {
+            let region = match province_entry.get_optional("region")? {
+                Some(region_entry) => {
+                    let region_name = region_entry.as_str()?;
+
+                    Some(regions.iter().position(|region| region.name == region_name)
+                        .ok_or_else(|| region_entry.error(format!("names unknown region `{region_name}`")))?)
+                },
+                None => None,
+            };
+            let population = match province_entry.get_optional("population")? {
+                Some(population_entry) => population_entry.as_u32()?,
+                None => 0,
+            };
+            let capital_entry = province_entry.get("capital")?;
+            let name_entry = province_entry.get("name")?;
+            let name = name_entry.as_str()?;
+            if provinces.iter().any(|province| province.name == name) {
+                return Err(name_entry.error(format!("province `{name}` is already defined")));
+            }
+
+            // Tiles are told apart only by color, so every province needs its own.
+            let color_entry = province_entry.get("color")?;
+            let color_text = color_entry.as_str()?;
+            let color = Self::color_from_data(&color_entry, color_text)?;
+            if Some(color) == empty_color {
+                return Err(color_entry.error(format!("`{color_text}` is `empty_color`, for tiles in no province")));
+            }
+            if let Some((_, other_province)) = key.iter().find(|(other_color, _)| *other_color == color) {
+                return Err(color_entry.error(format!("`{color_text}` is already the color of province `{}`", provinces[*other_province].name)));
+            }
+
+            key.push((color, provinces.len()));
+            provinces.push(Province {
+                name: name.to_string(),
+                capital: Self::coordinates_from_data(&capital_entry, shape)?,
+                region,
+                population: Population::new(population),
+            });
+            province_capital_entries.push(capital_entry);
+        }
+
+        let mut tile_provinces = vec![None; shape.tile_amount() as usize];
+        if let Some(image_entry) = entry.get_optional("image")? {
+            let image = ColorKeyImage::load(directory.join(image_entry.as_str()?), key)?;
+            check_dimensions(&image.path, &image.image, map_settings)?;
+
+            for (tile_index, tile_province) in tile_provinces.iter_mut().enumerate() {
+                let coordinates = shape.coordinates(tile_index);
+                let color = image.image.pixel(ImageDimensions::from_u32_tuple(coordinates.to_tuple()));
+
+                if color != empty_color {
+                    *tile_province = Some(image.value_at(coordinates)?);
+                }
+            }
+        }
+
+        for (province_index, province) in provinces.iter().enumerate() {
+            if tile_provinces[shape.index(province.capital)] != Some(province_index) {
+                return Err(province_capital_entries[province_index].error(format!("must be a tile of province `{}`", province.name)));
+            }
+        }
+        for (region_index, region) in regions.iter().enumerate() {
+            let capital_region = tile_provinces[shape.index(region.capital)].and_then(|province| provinces[province].region);
+
+            if capital_region != Some(region_index) {
+                return Err(region_capital_entries[region_index].error(format!("must be a tile of a province in region `{}`", region.name)));
+            }
+        }
+
+        Ok(Self::new(shape, provinces, regions, tile_provinces))
+    }
+
+    fn coordinates_from_data(entry: &DataEntry, shape: MapShape) -> CoreResult<TileCoordinates> {
+        let parts = entry.array()?;
+        if parts.len() != 2 {
+            return Err(entry.error("must be `[x, y]`"));
+        }
+
+        let coordinates = TileCoordinates::new(parts[0].as_u32()?, parts[1].as_u32()?);
+        if !shape.contains(coordinates) {
+            return Err(entry.error(format!("{coordinates} is outside of the map")));
+        }
+
+        Ok(coordinates)
+    }
+
+    fn color_from_data(entry: &DataEntry, color_text: &str) -> CoreResult<Rgb8> {
+
+        Rgb8::from_hex(color_text).ok_or_else(|| entry.error(format!("`{color_text}` isn't a color like `#ff8000`")))
+    }
+
+
+
+    pub fn provinces(&self) -> &[Province] {
+
+        &self.provinces
+    }
+
+    pub fn regions(&self) -> &[Region] {
+
+        &self.regions
+    }
+
+    pub fn province(&self, province: usize) -> Option<&Province> {
+
+        self.provinces.get(province)
+    }
+
+    pub fn region(&self, region: usize) -> Option<&Region> {
+
+        self.regions.get(region)
+    }
+
+    pub fn province_id(&self, name: &str) -> Option<usize> {
+
+        self.provinces.iter().position(|province| province.name == name)
+    }
+
+    pub fn region_id(&self, name: &str) -> Option<usize> {
+
+        self.regions.iter().position(|region| region.name == name)
+    }
+
+
+
+    /// `None` for tiles in no province, or outside of the map.
+    pub fn province_at(&self, coordinates: TileCoordinates) -> Option<usize> {
+
+        if self.shape.contains(coordinates) {
+            self.tile_provinces[self.shape.index(coordinates)]
+        } else {
+            None
+        }
+    }
+
+    pub fn region_at(&self, coordinates: TileCoordinates) -> Option<usize> {
+
+        self.provinces[self.province_at(coordinates)?].region
+    }
+
+    /// Empty for unknown provinces.
+    pub fn province_tiles(&self, province: usize) -> impl Iterator<Item = TileCoordinates> + '_ {
+
+        self.province_tile_indices(province).iter().map(|&tile_index| self.shape.coordinates(tile_index))
+    }
+
+    pub fn region_provinces(&self, region: usize) -> impl Iterator<Item = usize> + '_ {
+
+        self.provinces.iter().enumerate()
+            .filter(move |(_, province)| province.region == Some(region))
+            .map(|(province_index, _)| province_index)
+    }
+
+    pub fn region_tiles(&self, region: usize) -> impl Iterator<Item = TileCoordinates> + '_ {
+
+        self.region_provinces(region).flat_map(|province| self.province_tiles(province))
+    }
+
+    /// Provinces sharing an edge with the given one, sorted by id.
+    pub fn adjacent_provinces(&self, province: usize) -> &[usize] {
+
+        self.adjacent_provinces.get(province).map_or(&[], Vec::as_slice)
+    }
+
+    /// Regions with a province adjacent to any of the given region's ones, sorted by id.
+    pub fn adjacent_regions(&self, region: usize) -> Vec<usize> {
+        let mut adjacent_regions: Vec<usize> = self.region_provinces(region)
+            .flat_map(|province| self.adjacent_provinces(province))
+            .filter_map(|&province| self.provinces[province].region)
+            .filter(|&adjacent_region| adjacent_region != region)
+            .collect();
+
+        adjacent_regions.sort_unstable();
+        adjacent_regions.dedup();
+        adjacent_regions
+    }
+
+
+
+    pub fn region_population(&self, region: usize) -> u64 {
+
+        self.region_provinces(region).map(|province| self.provinces[province].population.amount as u64).sum()
+    }
+
+    pub(crate) fn province_tile_indices(&self, province: usize) -> &[usize] {
+
+        self.province_tiles.get(province).map_or(&[], Vec::as_slice)
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+    use crate::data::DataFile;
+    use crate::error::CoreError;
+    use crate::image::Image;
+    use crate::map::units::TerrainHeight;
+    use super::*;
+
+    const SHAPE: MapShape = MapShape::Rectangular { width: 4, height: 2 };
+
+    const REGIONS: &str = r##"
+        empty_color = "#ffffff"
+        [[region]]
+        name = "North"
+        capital = [0, 0]
+        [[region]]
+        name = "South"
+        capital = [2, 0]
+    "##;
+
+    const PROVINCES: &str = r##"
+        [[province]]
+        name = "A"
+        color = "#ff0000"
+        region = "North"
+        capital = [0, 0]
+        population = 100
+        [[province]]
+        name = "B"
+        color = "#00ff00"
+        region = "South"
+        capital = [3, 0]
+        population = 50
+        [[province]]
+        name = "C"
+        color = "#0000ff"
+        region = "North"
+        capital = [1, 1]
+        population = 7
+    "##;
+
+    fn load(directory: &Path, text: &str) -> CoreResult<Provinces> {
+        let file = DataFile::parse(directory.join("provinces.toml"), text)?;
+
+        Provinces::from_data(&file.root(), directory, &MapSettings::new(TerrainHeight::from_meters(1000), SHAPE))
+    }
+
+    fn assert_rejected(text: &str, expected_problem: &str) {
+        match load(Path::new(""), text) {
+            Err(CoreError::DataEntry { problem, .. }) => assert!(problem.contains(expected_problem), "`{problem}` instead of `{expected_problem}`"),
+            Err(error) => panic!("unexpected error {error}"),
+            Ok(_) => panic!("`{expected_problem}` was accepted"),
+        }
+    }
+
+    /// Provinces image in a directory of its own, laid out as below, with row 0 at the bottom, as images are read:
+    /// ```text
+    /// A C B -
+    /// A A B B
+    /// ```
+    fn write_image() -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("war_economy_provinces_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let (a, b, c, empty) = (Rgb8::new(255, 0, 0), Rgb8::new(0, 255, 0), Rgb8::new(0, 0, 255), Rgb8::new(255, 255, 255));
+        let pixels = vec![a, c, b, empty, a, a, b, b];
+        let image = Image::new_raw(pixels, ImageDimensions::new(4, 2)).unwrap();
+        image.save_png(File::create(directory.join("provinces.png")).unwrap()).unwrap();
+
+        directory
+    }
+
+    #[test]
+    fn tiles_and_population_are_grouped() {
+        let directory = write_image();
+        let provinces = load(&directory, &format!("image = \"provinces.png\"\n{REGIONS}{PROVINCES}")).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        let tile = TileCoordinates::new;
+        assert_eq!(provinces.province_tiles(0).collect::<Vec<_>>(), vec![tile(0, 0), tile(1, 0), tile(0, 1)]);
+        assert_eq!(provinces.region_tiles(0).collect::<Vec<_>>(), vec![tile(0, 0), tile(1, 0), tile(0, 1), tile(1, 1)]);
+        assert_eq!(provinces.region_tiles(1).collect::<Vec<_>>(), vec![tile(2, 0), tile(3, 0), tile(2, 1)]);
+        assert_eq!(provinces.province_tiles(3).count(), 0);
+        assert_eq!(provinces.region_tiles(2).count(), 0);
+
+        assert_eq!(provinces.province_at(tile(3, 1)), None);
+        assert_eq!(provinces.region_at(tile(1, 1)), Some(0));
+        assert_eq!(provinces.adjacent_provinces(0), &[1, 2]);
+        assert_eq!(provinces.adjacent_regions(0), vec![1]);
+
+        assert_eq!(provinces.region_population(0), 107);
+        assert_eq!(provinces.region_population(1), 50);
+        assert_eq!(provinces.region_population(2), 0);
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        assert_rejected(&format!("{REGIONS}[[region]]\nname = \"North\"\ncapital = [1, 0]\n"), "region `North` is already defined");
+        assert_rejected(&format!("{REGIONS}{PROVINCES}[[province]]\nname = \"B\"\ncolor = \"#000080\"\ncapital = [1, 0]\n"), "province `B` is already defined");
+    }
+
+    #[test]
+    fn shared_colors_are_rejected() {
+        assert_rejected(&format!("{REGIONS}{PROVINCES}[[province]]\nname = \"D\"\ncolor = \"#00FF00\"\ncapital = [1, 0]\n"), "already the color of province `B`");
+        assert_rejected(&format!("{REGIONS}[[province]]\nname = \"D\"\ncolor = \"#ffffff\"\ncapital = [1, 0]\n"), "is `empty_color`");
+    }
+
+}
@@ -0,0 +1,655 @@
+use std::fmt::{Display as FmtDisplay, Formatter};
+use std::io::{Read, Write};
+use std::slice::Iter;
+use std::sync::Arc;
+use crate::Definitions;
+use crate::error::{CoreError, CoreResult, ResultToCoreError};
+use crate::map::MapShape;
+use crate::map::climate::TileClimate;
+use crate::map::coordinates::{Direction, HexDirection, TileCoordinates};
+use crate::map::tile::{TileArray, TileLink, TileLinkMut, TileLocal, TileSizedData, TileWater};
+use crate::map::tile::surface::TileSurface;
+use crate::map::units::TerrainHeight;
+
+
+
+/// Chunks are squares with this many tiles on a side, except for ones cut by the map edge.
+pub const CHUNK_SIZE: u32 = 64;
+
+
+
+/// Position of a chunk on the grid of chunks, tile `(x, y)` lying in chunk `(x / CHUNK_SIZE, y / CHUNK_SIZE)`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct ChunkCoordinates {
+
+    pub x: u32,
+    pub y: u32,
+
+}
+
+impl ChunkCoordinates {
+
+    pub const fn new(x: u32, y: u32) -> Self {
+
+        Self {
+            x,
+            y,
+        }
+    }
+
+    pub const fn of_tile(coordinates: TileCoordinates) -> Self {
+
+        Self::new(coordinates.x / CHUNK_SIZE, coordinates.y / CHUNK_SIZE)
+    }
+
+    /// Tile in the chunk's corner, with the lowest coordinates.
+    pub const fn first_tile(self) -> TileCoordinates {
+
+        TileCoordinates::new(self.x * CHUNK_SIZE, self.y * CHUNK_SIZE)
+    }
+
+}
+
+impl FmtDisplay for ChunkCoordinates {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+
+        write!(formatter, "chunk ({}, {})", self.x, self.y)
+    }
+}
+
+
+
+/// Rectangle of map tiles, stored on its own, so it can be saved, loaded and redrawn alone.
+pub struct TileChunk {
+
+    coordinates: ChunkCoordinates,
+    width: u32,
+    height: u32,
+
+    tiles: TileArray,
+    /// Set by every mutable access to tiles, until cleared by the user.
+    dirty: bool,
+
+}
+
+impl TileChunk {
+
+    const MAGIC: &'static [u8; 4] = b"WECH";
+    const VERSION: u8 = 1;
+    const EMPTY_TYPE_ID: u32 = u32::MAX;
+    const AUTOMATIC_VARIANT: u32 = u32::MAX;
+
+    pub fn coordinates(&self) -> ChunkCoordinates {
+
+        self.coordinates
+    }
+
+    /// Width and height in tiles.
+    pub fn size(&self) -> (u32, u32) {
+
+        (self.width, self.height)
+    }
+
+    pub fn contains(&self, coordinates: TileCoordinates) -> bool {
+        let first_tile = self.coordinates.first_tile();
+
+        (first_tile.x..first_tile.x + self.width).contains(&coordinates.x)
+            && (first_tile.y..first_tile.y + self.height).contains(&coordinates.y)
+    }
+
+    pub fn is_dirty(&self) -> bool {
+
+        self.dirty
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty = false;
+    }
+
+
+
+    /// Takes map coordinates, `None` for tiles of other chunks.
+    pub fn get(&self, coordinates: TileCoordinates) -> Option<TileLink<'_>> {
+
+        self.tiles.get(self.local_index(coordinates)?)
+    }
+
+    /// Marks the chunk dirty.
+    pub fn get_mut(&mut self, coordinates: TileCoordinates) -> Option<TileLinkMut<'_>> {
+        let local_index = self.local_index(coordinates)?;
+        self.dirty = true;
+
+        self.tiles.get_mut(local_index)
+    }
+
+    /// Tiles with their map coordinates, row by row.
+    pub fn tiles(&self) -> impl Iterator<Item = (TileCoordinates, TileLink<'_>)> {
+        let (coordinates, width) = (self.coordinates, self.width);
+
+        self.tiles.iter().enumerate().map(move |(local_index, tile)| (Self::tile_coordinates(coordinates, width, local_index), tile))
+    }
+
+    /// Marks the chunk dirty.
+    pub fn tiles_mut(&mut self) -> impl Iterator<Item = (TileCoordinates, TileLinkMut<'_>)> {
+        let (coordinates, width) = (self.coordinates, self.width);
+        self.dirty = true;
+
+        self.tiles.iter_mut().enumerate().map(move |(local_index, tile)| (Self::tile_coordinates(coordinates, width, local_index), tile))
+    }
+
+    fn local_index(&self, coordinates: TileCoordinates) -> Option<u32> {
+        if !self.contains(coordinates) {
+            return None
+        }
+        let first_tile = self.coordinates.first_tile();
+
+        Some((coordinates.y - first_tile.y) * self.width + coordinates.x - first_tile.x)
+    }
+
+    /// Sized parts and surfaces of a single row of the chunk's tiles.
+    fn row(&self, local_y: u32) -> (&[TileSizedData], &[TileSurface]) {
+        let row_start = (local_y * self.width) as usize;
+        let row_end = row_start + self.width as usize;
+        let layer_amount = self.tiles.layer_amount();
+
+        (&self.tiles.main_slice()[row_start..row_end], &self.tiles.surface_slice()[row_start * layer_amount..row_end * layer_amount])
+    }
+
+    fn tile_coordinates(coordinates: ChunkCoordinates, width: u32, local_index: usize) -> TileCoordinates {
+        let first_tile = coordinates.first_tile();
+
+        TileCoordinates::new(first_tile.x + local_index as u32 % width, first_tile.y + local_index as u32 / width)
+    }
+
+
+
+    /// Little endian binary, with a header of the chunk's coordinates and size, then tiles row by row.
+    /// The dirty flag is left as it is, as only the user knows, what the chunk was written for.
+    pub fn write(&self, writer: &mut impl Write) -> CoreResult<()> {
+        let mut bytes = Vec::with_capacity(25 + self.tiles.len() * (21 + 8 * self.tiles.layer_amount()));
+
+        bytes.extend_from_slice(Self::MAGIC);
+        bytes.push(Self::VERSION);
+        for header_value in [self.coordinates.x, self.coordinates.y, self.width, self.height, self.tiles.layer_amount() as u32] {
+            bytes.extend_from_slice(&header_value.to_le_bytes());
+        }
+
+        for tile in &self.tiles {
+            bytes.extend_from_slice(&tile.main.height.to_meters().to_le_bytes());
+            bytes.extend_from_slice(&(tile.main.owner as u64).to_le_bytes());
+            bytes.push(tile.main.water.bits());
+            bytes.extend_from_slice(&tile.main.climate.temperature.to_le_bytes());
+            bytes.extend_from_slice(&tile.main.climate.precipitation.to_le_bytes());
+
+            for surface in tile.surface {
+                let type_id = if surface.is_empty() { Self::EMPTY_TYPE_ID } else { surface.type_id() as u32 };
+                let variant = if surface.is_automatic() { Self::AUTOMATIC_VARIANT } else { surface.variant() as u32 };
+
+                bytes.extend_from_slice(&type_id.to_le_bytes());
+                bytes.extend_from_slice(&variant.to_le_bytes());
+            }
+        }
+
+        writer.write_all(&bytes).to_core_error()
+    }
+
+}
+
+
+
+/// Tiles of a whole map, split into chunks of `CHUNK_SIZE` squared tiles, stored row by row.
+pub struct TileChunks {
+
+    shape: MapShape,
+    layer_amount: usize,
+    chunk_columns: u32,
+    chunks: Vec<TileChunk>,
+
+}
+
+impl TileChunks {
+
+    pub fn new(definitions: Arc<Definitions>, shape: MapShape, filler: TileLocal) -> Self {
+        let (width, height) = shape.max_axis();
+        let chunk_columns = width.div_ceil(CHUNK_SIZE);
+        let chunk_rows = height.div_ceil(CHUNK_SIZE);
+        let mut chunks = vec![];
+
+        for chunk_y in 0..chunk_rows {
+            for chunk_x in 0..chunk_columns {
+                let coordinates = ChunkCoordinates::new(chunk_x, chunk_y);
+                let first_tile = coordinates.first_tile();
+                let chunk_width = CHUNK_SIZE.min(width - first_tile.x);
+                let chunk_height = CHUNK_SIZE.min(height - first_tile.y);
+
+                chunks.push(TileChunk {
+                    coordinates,
+                    width: chunk_width,
+                    height: chunk_height,
+
+                    tiles: TileArray::new(definitions.clone(), filler.clone(), chunk_width * chunk_height),
+                    dirty: false,
+                });
+            }
+        }
+
+        Self {
+            shape,
+            layer_amount: definitions.surface_types.layers.len(),
+            chunk_columns,
+            chunks,
+        }
+    }
+
+    /// `tiles` go in the order of map indexes, see `MapShape::index`, ones missing from the end are left as `filler`.
+    pub fn from_tiles(definitions: Arc<Definitions>, shape: MapShape, filler: TileLocal, tiles: &[TileLocal]) -> Self {
+        let mut chunks = Self::new(definitions, shape, filler);
+
+        for chunk in &mut chunks.chunks {
+            for local_index in 0..chunk.tiles.len() {
+                let coordinates = TileChunk::tile_coordinates(chunk.coordinates, chunk.width, local_index);
+                let Some(tile) = tiles.get(shape.index(coordinates)) else { continue };
+
+                let _ = chunk.tiles.put(local_index as u32, tile.clone());
+            }
+        }
+
+        chunks
+    }
+
+
+
+    pub fn shape(&self) -> MapShape {
+
+        self.shape
+    }
+
+    pub fn len(&self) -> usize {
+
+        self.shape.tile_amount() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+
+        self.shape.tile_amount() == 0
+    }
+
+    /// Row by row, from the chunk with the first map tile.
+    pub fn chunks(&self) -> &[TileChunk] {
+
+        &self.chunks
+    }
+
+    pub fn chunk(&self, coordinates: ChunkCoordinates) -> Option<&TileChunk> {
+
+        Some(&self.chunks[self.chunk_index(coordinates)?])
+    }
+
+    /// Tiles changed through the chunk mark it dirty.
+    pub fn chunk_mut(&mut self, coordinates: ChunkCoordinates) -> Option<&mut TileChunk> {
+        let chunk_index = self.chunk_index(coordinates)?;
+
+        Some(&mut self.chunks[chunk_index])
+    }
+
+    pub fn dirty_chunks(&self) -> impl Iterator<Item = ChunkCoordinates> + '_ {
+
+        self.chunks.iter().filter(|chunk| chunk.dirty).map(TileChunk::coordinates)
+    }
+
+    /// Dirty chunks, clearing their flags.
+    pub fn take_dirty_chunks(&mut self) -> Vec<ChunkCoordinates> {
+        let dirty_chunks = self.dirty_chunks().collect();
+
+        for chunk in &mut self.chunks {
+            chunk.clear_dirty();
+        }
+
+        dirty_chunks
+    }
+
+
+
+    /// Fails for chunks outside of the map.
+    pub fn write_chunk(&self, coordinates: ChunkCoordinates, writer: &mut impl Write) -> CoreResult<()> {
+
+        self.chunk(coordinates)
+            .ok_or_else(|| CoreError::InvalidChunk { problem: format!("{coordinates} is outside of the map") })?
+            .write(writer)
+    }
+
+    /// Replaces the chunk, written by `TileChunk::write`, marking it dirty.
+    /// Its size and number of surface layers must match this map, and every tile must pass `check_tile`.
+    pub fn read_chunk(&mut self, reader: &mut impl Read, check_tile: impl Fn(TileCoordinates, &TileLocal) -> CoreResult<()>) -> CoreResult<ChunkCoordinates> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).to_core_error()?;
+        let version = read_u8(reader)?;
+        if &magic != TileChunk::MAGIC || version != TileChunk::VERSION {
+            return Err(CoreError::InvalidChunk { problem: format!("unknown format, or version {version}") });
+        }
+
+        let coordinates = ChunkCoordinates::new(read_u32(reader)?, read_u32(reader)?);
+        let size = (read_u32(reader)?, read_u32(reader)?);
+        let layer_amount = read_u32(reader)? as usize;
+
+        let chunk_index = self.chunk_index(coordinates)
+            .ok_or_else(|| CoreError::InvalidChunk { problem: format!("{coordinates} is outside of the map") })?;
+        let chunk = &mut self.chunks[chunk_index];
+        if size != chunk.size() || layer_amount != self.layer_amount {
+            return Err(CoreError::InvalidChunk {
+                problem: format!("{coordinates} has {}x{} tiles in {layer_amount} layers, instead of {}x{} in {}", size.0, size.1, chunk.width, chunk.height, self.layer_amount),
+            });
+        }
+
+        let mut tiles = vec![];
+        for local_index in 0..chunk.tiles.len() {
+            let main = TileSizedData {
+                height: TerrainHeight::from_meters(read_u32(reader)? as i32),
+                owner: read_u64(reader)? as usize,
+                water: TileWater::from_bits(read_u8(reader)?),
+                climate: TileClimate::new(f32::from_bits(read_u32(reader)?), f32::from_bits(read_u32(reader)?)),
+            };
+            let mut surface = Vec::with_capacity(layer_amount);
+            for _ in 0..layer_amount {
+                let (type_id, variant) = (read_u32(reader)?, read_u32(reader)?);
+
+                surface.push(match (type_id, variant) {
+                    (TileChunk::EMPTY_TYPE_ID, _) => TileSurface::EMPTY,
+                    (_, TileChunk::AUTOMATIC_VARIANT) => TileSurface::new_automatic(type_id as usize),
+                    _ => TileSurface::new(type_id as usize, variant as usize),
+                });
+            }
+
+            let tile = TileLocal { main, surface: surface.into_boxed_slice() };
+            let tile_coordinates = TileChunk::tile_coordinates(coordinates, chunk.width, local_index);
+            check_tile(tile_coordinates, &tile)
+                .map_err(|problem| CoreError::InvalidChunk { problem: format!("{coordinates} has invalid tile {tile_coordinates}, {problem}") })?;
+
+            tiles.push(tile);
+        }
+
+        // Only put in, once the whole chunk was read, so failed reads leave it untouched.
+        for (local_index, tile) in tiles.into_iter().enumerate() {
+            let _ = chunk.tiles.put(local_index as u32, tile);
+        }
+        chunk.dirty = true;
+
+        Ok(coordinates)
+    }
+
+
+
+    /// Takes a map index, see `MapShape::index`.
+    pub fn get(&self, index: u32) -> Option<TileLink<'_>> {
+
+        self.get_at(self.shape.coordinates(index as usize))
+    }
+
+    pub fn index(&self, index: u32) -> TileLink<'_> {
+
+        self.get(index).unwrap_or_else(|| panic!("index {index} out of bounds, for {} length", self.len()))
+    }
+
+    /// Marks the tile's chunk dirty.
+    pub fn main_mut(&mut self, index: u32) -> Option<&mut TileSizedData> {
+
+        Some(self.get_at_mut(self.shape.coordinates(index as usize))?.main)
+    }
+
+    /// `None` for coordinates outside of the map.
+    pub fn get_at(&self, coordinates: TileCoordinates) -> Option<TileLink<'_>> {
+
+        self.chunk(ChunkCoordinates::of_tile(coordinates))?.get(coordinates)
+    }
+
+    /// Marks the tile's chunk dirty.
+    pub fn get_at_mut(&mut self, coordinates: TileCoordinates) -> Option<TileLinkMut<'_>> {
+
+        self.chunk_mut(ChunkCoordinates::of_tile(coordinates))?.get_mut(coordinates)
+    }
+
+    pub fn neighbour(&self, coordinates: TileCoordinates, direction: Direction) -> Option<TileLink<'_>> {
+
+        self.get_at(self.shape.neighbour(coordinates, direction)?)
+    }
+
+    pub fn hex_neighbour(&self, coordinates: TileCoordinates, direction: HexDirection) -> Option<TileLink<'_>> {
+
+        self.get_at(self.shape.hex_neighbour(coordinates, direction)?)
+    }
+
+    /// Tiles sharing an edge with the given one, see `MapShape::adjacent`.
+    pub fn neighbours(&self, coordinates: TileCoordinates) -> impl Iterator<Item = (TileCoordinates, TileLink<'_>)> {
+
+        self.shape.adjacent(coordinates).map(move |neighbour| (neighbour, self.index(self.shape.index(neighbour) as u32)))
+    }
+
+    /// Tiles in the order of map indexes, see `MapShape::index`, use `chunks` to go chunk by chunk.
+    pub fn iter(&self) -> TileChunksIterator<'_> {
+
+        self.into_iter()
+    }
+
+
+
+    fn chunk_index(&self, coordinates: ChunkCoordinates) -> Option<usize> {
+        if coordinates.x >= self.chunk_columns {
+            return None
+        }
+        let chunk_index = (coordinates.y * self.chunk_columns + coordinates.x) as usize;
+
+        (chunk_index < self.chunks.len()).then_some(chunk_index)
+    }
+
+}
+
+impl<'a> IntoIterator for &'a TileChunks {
+    type Item = TileLink<'a>;
+    type IntoIter = TileChunksIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+
+        TileChunksIterator {
+            tiles: self,
+            row_start: 0,
+            chunk_index: 0,
+            local_y: 0,
+            main: [].iter(),
+            surfaces: &[],
+        }
+    }
+}
+
+
+
+/// Walks the arrays of chunks directly, a row of tiles from each chunk in a row of chunks at a time.
+pub struct TileChunksIterator<'a> {
+
+    tiles: &'a TileChunks,
+    /// Index of the first chunk in the row of chunks being walked.
+    row_start: usize,
+    /// Chunk to take the next row of tiles from.
+    chunk_index: usize,
+    local_y: u32,
+
+    main: Iter<'a, TileSizedData>,
+    /// Surfaces of tiles in the current row not given out yet.
+    surfaces: &'a [TileSurface],
+
+}
+
+impl<'a> Iterator for TileChunksIterator<'a> {
+    type Item = TileLink<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let layer_amount = self.tiles.layer_amount;
+
+        loop {
+            if let Some(main) = self.main.next() {
+                let (surface, rest) = self.surfaces.split_at(layer_amount);
+                self.surfaces = rest;
+
+                return Some(TileLink {
+                    main,
+                    surface,
+                });
+            }
+
+            let chunk = self.tiles.chunks.get(self.chunk_index)?;
+            let (main, surfaces) = chunk.row(self.local_y);
+            self.main = main.iter();
+            self.surfaces = surfaces;
+
+            self.chunk_index += 1;
+            if self.chunk_index == self.row_start + self.tiles.chunk_columns as usize {
+                self.chunk_index = self.row_start;
+                self.local_y += 1;
+
+                if self.local_y == chunk.height {
+                    self.row_start += self.tiles.chunk_columns as usize;
+                    self.chunk_index = self.row_start;
+                    self.local_y = 0;
+                }
+            }
+        }
+    }
+}
+
+
+
+fn read_u8(reader: &mut impl Read) -> CoreResult<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes).to_core_error()?;
+
+    Ok(bytes[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> CoreResult<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes).to_core_error()?;
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> CoreResult<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes).to_core_error()?;
+
+    Ok(u64::from_le_bytes(bytes))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::path::Path;
+    use crate::map::{Map, MapSettings};
+    use super::*;
+
+    const POWER_AMOUNT: usize = 2;
+
+    /// Two chunks across, the second cut by the map edge to 6 tiles wide.
+    fn test_map() -> Map {
+        let definitions = Arc::new(Definitions::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../game sets/historical")).unwrap());
+        let properties = MapSettings::new(TerrainHeight::from_meters(1000), MapShape::Rectangular { width: CHUNK_SIZE + 6, height: 3 });
+        let filler = TileLocal::new(0, vec![TileSurface::new_automatic(0), TileSurface::EMPTY].into_boxed_slice());
+
+        Map::new(definitions, properties, filler)
+    }
+
+    fn assert_same_tiles(first: &Map, second: &Map) {
+        for (first_tile, second_tile) in first.get_terrain().1.iter().zip(second.get_terrain().1) {
+            assert_eq!(first_tile.main.height.to_meters(), second_tile.main.height.to_meters());
+            assert_eq!(first_tile.main.owner, second_tile.main.owner);
+            assert_eq!(first_tile.water(), second_tile.water());
+            assert_eq!(first_tile.climate(), second_tile.climate());
+            assert_eq!(first_tile.surface, second_tile.surface);
+        }
+    }
+
+    #[test]
+    fn tiles_iterate_in_map_order() {
+        let definitions = Arc::new(Definitions::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../game sets/historical")).unwrap());
+        let shape = MapShape::Cylindrical { width: CHUNK_SIZE + 6, height: CHUNK_SIZE + 2 };
+        let mut filler = TileLocal::new(0, vec![TileSurface::new_automatic(0), TileSurface::EMPTY].into_boxed_slice());
+        filler.main.height = TerrainHeight::from_meters(-1);
+
+        // The last few tiles are missing, so they are left as the filler.
+        let given_amount = shape.tile_amount() as usize - 10;
+        let tiles: Vec<TileLocal> = (0..given_amount).map(|tile_index| {
+            let mut tile = filler.clone();
+            tile.main.height = TerrainHeight::from_meters(tile_index as i32);
+            tile.surface[1] = TileSurface::new(0, tile_index % 3);
+            tile
+        }).collect();
+
+        let chunks = TileChunks::from_tiles(definitions, shape, filler, &tiles);
+        assert_eq!(chunks.chunks().len(), 4);
+        assert_eq!(chunks.iter().count(), shape.tile_amount() as usize);
+
+        for (tile_index, tile) in chunks.iter().enumerate() {
+            if tile_index < given_amount {
+                assert_eq!(tile.main.height.to_meters(), tile_index as i32);
+                assert_eq!(tile.surface[1], TileSurface::new(0, tile_index % 3));
+            } else {
+                assert_eq!(tile.main.height.to_meters(), -1);
+                assert!(tile.surface[1].is_empty());
+            }
+            assert_eq!(chunks.index(tile_index as u32).main.height.to_meters(), tile.main.height.to_meters());
+        }
+    }
+
+    #[test]
+    fn chunk_round_trip() {
+        let mut source = test_map();
+        let edge_tile = TileCoordinates::new(CHUNK_SIZE + 5, 2);
+        source.set_surface(edge_tile, 0, TileSurface::new(2, 1)).unwrap();
+        source.set_surface(edge_tile, 1, TileSurface::new_automatic(0)).unwrap();
+        source.set_surface(TileCoordinates::new(CHUNK_SIZE, 0), 0, TileSurface::new(1, 0)).unwrap();
+        *source.height_mut(edge_tile).unwrap() = TerrainHeight::from_meters(-120);
+        *source.water_mut(edge_tile).unwrap() = TileWater::RIVER;
+        *source.climate_mut(edge_tile).unwrap() = TileClimate::new(-3.5, 850.0);
+
+        let cut_chunk = ChunkCoordinates::of_tile(edge_tile);
+        assert_eq!(source.get_terrain().1.chunk(cut_chunk).unwrap().size(), (6, 3));
+
+        let mut bytes = vec![];
+        source.write_chunk(cut_chunk, &mut bytes).unwrap();
+
+        let mut target = test_map();
+        target.take_dirty_chunks();
+        assert_eq!(target.read_chunk(&mut bytes.as_slice(), POWER_AMOUNT).unwrap(), cut_chunk);
+        assert_eq!(target.take_dirty_chunks(), vec![cut_chunk]);
+        assert_same_tiles(&source, &target);
+
+        let loaded_tile = target.tile(edge_tile).unwrap();
+        assert!(loaded_tile.surface[1].is_automatic());
+        assert!(target.tile(TileCoordinates::new(CHUNK_SIZE + 1, 1)).unwrap().surface[1].is_empty());
+    }
+
+    #[test]
+    fn invalid_chunks_are_rejected_whole() {
+        let mut source = test_map();
+        let chunk = ChunkCoordinates::new(1, 0);
+        source.set_surface(TileCoordinates::new(CHUNK_SIZE, 0), 0, TileSurface::new(1, 0)).unwrap();
+        let mut bytes = vec![];
+        source.write_chunk(chunk, &mut bytes).unwrap();
+
+        // Type id of the last tile's first layer, which only has 3 types.
+        let last_tile_type = bytes.len() - 16;
+        let mut unknown_type = bytes.clone();
+        unknown_type[last_tile_type..last_tile_type + 4].copy_from_slice(&7_u32.to_le_bytes());
+        // Owner of the first tile, right after the 25 bytes of the header and its height.
+        let mut unknown_owner = bytes.clone();
+        unknown_owner[29..37].copy_from_slice(&(POWER_AMOUNT as u64).to_le_bytes());
+
+        let mut target = test_map();
+        for invalid_bytes in [unknown_type, unknown_owner, bytes[..bytes.len() - 1].to_vec()] {
+            assert!(target.read_chunk(&mut invalid_bytes.as_slice(), POWER_AMOUNT).is_err());
+        }
+        assert!(target.take_dirty_chunks().is_empty());
+        assert_eq!(target.tile(TileCoordinates::new(CHUNK_SIZE, 0)).unwrap().surface[0], TileSurface::new_automatic(0));
+    }
+
+}
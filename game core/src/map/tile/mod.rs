@@ -1,89 +1,189 @@
-use std::mem::{size_of, transmute};
+use std::mem::take;
+use std::ops::Range;
+use std::slice::{Iter, IterMut};
 use std::sync::Arc;
 use crate::Definitions;
+use crate::map::MapShape;
+use crate::map::climate::TileClimate;
+use crate::map::coordinates::{Direction, HexDirection, TileCoordinates};
 use crate::map::tile::surface::{TileSurface};
 use crate::map::units::TerrainHeight;
 
+pub mod chunks;
 pub mod sectors;
 pub mod surface;
 
 
 
+/// Tiles kept as two parallel arrays, sized parts of every tile and their surfaces, `layer_amount` per tile.
 pub struct TileArray {
 
-    definitions: Arc<Definitions>,
     tile_amount: u32,
-    surface_byte_size: usize,
-    tile_byte_size: usize,
+    layer_amount: usize,
 
-    byte_array: Box<[u8]>,
+    main: Box<[TileSizedData]>,
+    surfaces: Box<[TileSurface]>,
 
 }
 
 impl TileArray {
 
     pub fn new(definitions: Arc<Definitions>, filler: TileLocal, tile_amount: u32) -> Self {
-        let surface_byte_size = size_of::<TileSurface>() * definitions.surface_types.layers.len();
-        let tile_byte_size = size_of::<TileSizedData>() + surface_byte_size;
-        let byte_array = vec![0; tile_amount as usize * tile_byte_size].into_boxed_slice();
-        let mut result = Self {
-            definitions,
-            tile_amount,
-            surface_byte_size,
-            tile_byte_size,
+        let layer_amount = definitions.surface_types.layers.len();
+        let mut filler_surface = filler.surface.into_vec();
+        filler_surface.resize(layer_amount, TileSurface::EMPTY);
 
-            byte_array,
-        };
+        Self {
+            tile_amount,
+            layer_amount,
 
-        for tile_index in 0..tile_amount {
-            let _ = result.put(tile_index, filler.clone());
+            main: vec![filler.main; tile_amount as usize].into_boxed_slice(),
+            surfaces: filler_surface.iter().cycle().take(tile_amount as usize * layer_amount).cloned().collect(),
         }
-
-        result
     }
 
 
 
+    /// Fails for indexes out of bounds, or surfaces not matching the number of layers.
     pub fn put(&mut self, index: u32, new_tile: TileLocal) -> Result<(), ()> {
-        if index >= self.tile_amount {
+        if new_tile.surface.len() != self.layer_amount {
             return Err(())
         }
+        let tile = self.get_mut(index).ok_or(())?;
 
-        unsafe {
-            new_tile.write_memory(&mut self.byte_array[self.byte_index(index)] as *mut u8);
-        }
+        *tile.main = new_tile.main;
+        tile.surface.clone_from_slice(&new_tile.surface);
 
         Ok(())
     }
 
 
 
-    pub fn get(&self, index: u32) -> Option<TileLink> {
-        let byte_index = self.byte_index(index);
+    pub fn get(&self, index: u32) -> Option<TileLink<'_>> {
+        let tile_index = index as usize;
 
+        Some(TileLink {
+            main: self.main.get(tile_index)?,
+            surface: &self.surfaces[self.surface_range(tile_index)],
+        })
+    }
+
+    pub fn get_mut(&mut self, index: u32) -> Option<TileLinkMut<'_>> {
+        let tile_index = index as usize;
+        let surface_range = self.surface_range(tile_index);
 
-        if byte_index < self.byte_array.len() {
-            let result = unsafe { TileLink {
-                main: transmute(&self.byte_array[byte_index]),
-                surface: transmute((&self.byte_array[size_of::<TileSizedData>() + byte_index], self.definitions.surface_types.layers.len())),
-            }};
+        Some(TileLinkMut {
+            main: self.main.get_mut(tile_index)?,
+            surface: &mut self.surfaces[surface_range],
+        })
+    }
 
-            Some(result)
+    pub fn main_mut(&mut self, index: u32) -> Option<&mut TileSizedData> {
+
+        self.main.get_mut(index as usize)
+    }
+
+    pub fn index(&self, index: u32) -> TileLink<'_> {
+
+        self.get(index).unwrap_or_else(|| panic!("index {index} out of bounds, for {} length", self.tile_amount))
+    }
+
+    pub fn index_mut(&mut self, index: u32) -> TileLinkMut<'_> {
+        let tile_amount = self.tile_amount;
+
+        self.get_mut(index).unwrap_or_else(|| panic!("index {index} out of bounds, for {tile_amount} length"))
+    }
+
+    /// Sized parts of every tile, in index order.
+    pub fn main_slice(&self) -> &[TileSizedData] {
+
+        &self.main
+    }
+
+    /// Surfaces of every tile, in index order, `layer_amount` per tile.
+    pub fn surface_slice(&self) -> &[TileSurface] {
+
+        &self.surfaces
+    }
+
+    pub fn layer_amount(&self) -> usize {
+
+        self.layer_amount
+    }
+
+    pub fn len(&self) -> usize {
+
+        self.tile_amount as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+
+        self.tile_amount == 0
+    }
+
+    pub fn iter(&self) -> TileArrayIterator<'_> {
+
+        self.into_iter()
+    }
+
+    /// Tiles in index order, with both parts changeable in place.
+    pub fn iter_mut(&mut self) -> TileArrayIteratorMut<'_> {
+
+        TileArrayIteratorMut {
+            main: self.main.iter_mut(),
+            surfaces: &mut self.surfaces,
+            layer_amount: self.layer_amount,
+        }
+    }
+
+
+
+    /// `None` for coordinates outside of the shape.
+    pub fn get_at(&self, shape: &MapShape, coordinates: TileCoordinates) -> Option<TileLink<'_>> {
+
+        if shape.contains(coordinates) {
+            self.get(shape.index(coordinates) as u32)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_at_mut(&mut self, shape: &MapShape, coordinates: TileCoordinates) -> Option<TileLinkMut<'_>> {
+
+        if shape.contains(coordinates) {
+            self.get_mut(shape.index(coordinates) as u32)
         } else {
             None
         }
     }
 
-    pub fn index(&self, index: u32) -> TileLink {
+    pub fn neighbour(&self, shape: &MapShape, coordinates: TileCoordinates, direction: Direction) -> Option<TileLink<'_>> {
 
-        self.get(index).expect(&format!("index {index} out of bounds, for {} length", self.tile_amount))
+        self.get_at(shape, shape.neighbour(coordinates, direction)?)
     }
 
+    pub fn hex_neighbour(&self, shape: &MapShape, coordinates: TileCoordinates, direction: HexDirection) -> Option<TileLink<'_>> {
 
+        self.get_at(shape, shape.hex_neighbour(coordinates, direction)?)
+    }
 
-    fn byte_index(&self, full_index: u32) -> usize {
+    /// Tiles sharing an edge with the given one, see `MapShape::adjacent`.
+    pub fn neighbours<'a>(&'a self, shape: &MapShape, coordinates: TileCoordinates) -> impl Iterator<Item = (TileCoordinates, TileLink<'a>)> {
+        let shape = *shape;
+
+        shape.adjacent(coordinates).map(move |neighbour| (neighbour, self.index(shape.index(neighbour) as u32)))
+    }
+
+
+
+    /// Empty for tiles out of bounds.
+    fn surface_range(&self, tile_index: usize) -> Range<usize> {
+        if tile_index >= self.tile_amount as usize {
+            return 0..0
+        }
+        let start = tile_index * self.layer_amount;
 
-        full_index as usize * self.tile_byte_size
+        start..start + self.layer_amount
     }
 
 }
@@ -95,19 +195,31 @@ impl<'a> IntoIterator for &'a TileArray {
     fn into_iter(self) -> Self::IntoIter {
 
         TileArrayIterator {
-            current_index: 0,
-            tiles: self,
+            main: self.main.iter(),
+            surfaces: &self.surfaces,
+            layer_amount: self.layer_amount,
         }
     }
 }
 
+impl<'a> IntoIterator for &'a mut TileArray {
+    type Item = TileLinkMut<'a>;
+    type IntoIter = TileArrayIteratorMut<'a>;
 
+    fn into_iter(self) -> Self::IntoIter {
 
-pub struct TileArrayIterator<'a> {
+        self.iter_mut()
+    }
+}
 
-    current_index: u32,
 
-    tiles: &'a TileArray,
+
+pub struct TileArrayIterator<'a> {
+
+    main: Iter<'a, TileSizedData>,
+    /// Surfaces of tiles not given out yet.
+    surfaces: &'a [TileSurface],
+    layer_amount: usize,
 
 }
 
@@ -115,14 +227,40 @@ impl<'a> Iterator for TileArrayIterator<'a> {
     type Item = TileLink<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current_index == self.tiles.tile_amount {
+        let main = self.main.next()?;
+        let (surface, rest) = self.surfaces.split_at(self.layer_amount);
+        self.surfaces = rest;
 
-            return None
-        }
-        let result = self.tiles.index(self.current_index);
-        self.current_index += 1;
+        Some(TileLink {
+            main,
+            surface,
+        })
+    }
+}
+
+
+
+pub struct TileArrayIteratorMut<'a> {
+
+    main: IterMut<'a, TileSizedData>,
+    /// Surfaces of tiles not given out yet.
+    surfaces: &'a mut [TileSurface],
+    layer_amount: usize,
+
+}
+
+impl<'a> Iterator for TileArrayIteratorMut<'a> {
+    type Item = TileLinkMut<'a>;
 
-        Some(result)
+    fn next(&mut self) -> Option<Self::Item> {
+        let main = self.main.next()?;
+        let (surface, rest) = take(&mut self.surfaces).split_at_mut(self.layer_amount);
+        self.surfaces = rest;
+
+        Some(TileLinkMut {
+            main,
+            surface,
+        })
     }
 }
 
@@ -144,29 +282,13 @@ impl TileLocal {
             main: TileSizedData {
                 height: TerrainHeight::from_meters(10),
                 owner,
+                water: TileWater::NONE,
+                climate: TileClimate::default(),
             },
             surface,
         }
     }
 
-
-
-    pub unsafe fn write_memory<P>(&self, pointer: * mut P) {
-
-        (pointer as * mut TileSizedData).write(self.main.clone());
-
-        for (surface_index, new_surface) in self.surface.iter().enumerate() {
-            (
-                (
-                    pointer as usize
-                        + surface_index * size_of::<TileSurface>()
-                        + size_of::<TileSizedData>()
-                ) as * mut TileSurface
-            ).write(new_surface.clone());
-        }
-
-    }
-
 }
 
 
@@ -189,6 +311,51 @@ impl<'a> TileLink<'a> {
         }
     }
 
+    pub fn water(&self) -> TileWater {
+
+        self.main.water
+    }
+
+    pub fn climate(&self) -> TileClimate {
+
+        self.main.climate
+    }
+
+}
+
+
+
+/// Tile changeable in place, see `TileArray::get_mut`.
+pub struct TileLinkMut<'a> {
+
+    pub main: &'a mut TileSizedData,
+    pub surface: &'a mut [TileSurface],
+
+}
+
+impl<'a> TileLinkMut<'a> {
+
+    pub fn as_link(&self) -> TileLink<'_> {
+
+        TileLink {
+            main: self.main,
+            surface: self.surface,
+        }
+    }
+
+    pub fn to_local(&self) -> TileLocal {
+
+        self.as_link().to_local()
+    }
+
+    /// Changes a single layer, leaving others as they are, `false` if the tile has no such layer.
+    pub fn set_surface(&mut self, layer: usize, surface: TileSurface) -> bool {
+        let Some(layer_surface) = self.surface.get_mut(layer) else { return false };
+        *layer_surface = surface;
+
+        true
+    }
+
 }
 
 
@@ -200,4 +367,74 @@ pub struct TileSizedData {
 
     pub owner: usize,
 
+    pub water: TileWater,
+    pub climate: TileClimate,
+
+}
+
+
+
+/// Water on a tile, derived from heights by `HydrologySettings::apply`.
+/// Several can be set at once, like a river flowing along a coast.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct TileWater ( u8);
+
+impl TileWater {
+
+    pub const NONE: TileWater = TileWater ( 0);
+    /// Below the sea level.
+    pub const SEA: TileWater = TileWater ( 1);
+    /// Flooded pit, with no way out lower than its water level.
+    pub const LAKE: TileWater = TileWater ( 2);
+    pub const RIVER: TileWater = TileWater ( 4);
+    /// Land next to the sea.
+    pub const COAST: TileWater = TileWater ( 8);
+
+    pub const fn from_bits(bits: u8) -> Self {
+
+        Self ( bits)
+    }
+
+    pub const fn bits(self) -> u8 {
+
+        self.0
+    }
+
+    pub const fn contains(self, other: TileWater) -> bool {
+
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: TileWater) {
+        self.0 |= other.0;
+    }
+
+
+
+    pub const fn is_sea(self) -> bool {
+
+        self.contains(TileWater::SEA)
+    }
+
+    pub const fn is_lake(self) -> bool {
+
+        self.contains(TileWater::LAKE)
+    }
+
+    pub const fn is_river(self) -> bool {
+
+        self.contains(TileWater::RIVER)
+    }
+
+    pub const fn is_coast(self) -> bool {
+
+        self.contains(TileWater::COAST)
+    }
+
+    /// Sea or lake, covering the whole tile.
+    pub const fn is_open_water(self) -> bool {
+
+        self.is_sea() || self.is_lake()
+    }
+
 }
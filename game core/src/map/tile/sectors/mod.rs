@@ -1,5 +1,10 @@
+use std::collections::BTreeMap;
+use crate::data::DataEntry;
+use crate::error::CoreResult;
+use crate::map::tile::TileLink;
+use crate::map::tile::chunks::TileChunks;
 use crate::population::Population;
-use crate::units::Time;
+use crate::units::{GameDate, TimeSpan};
 
 
 
@@ -25,31 +30,146 @@ impl TileSector {
 
 pub struct TileSectorType {
 
-    pub name: &'static str,
-    pub reward_frequency: Time,
-    pub reward_offset: Time,
+    pub name: String,
+    pub reward_frequency: TimeSpan,
+    pub reward_offset: TimeSpan,
+
+    /// Multiplies rewards of sectors on tiles with a river.
+    pub river_yield: f32,
+    /// Multiplies rewards of sectors on tiles next to the sea.
+    pub coast_yield: f32,
+    /// Temperature of the best rewards, falling to none at `temperature_tolerance` degrees away.
+    pub ideal_temperature: Option<f32>,
+    pub temperature_tolerance: f32,
+    /// Yearly rain needed for full rewards, less rain giving proportionally less.
+    pub needed_precipitation: Option<f32>,
 
 }
 
 impl TileSectorType {
 
-    pub fn new(name: &'static str, reward_frequency: Time, reward_offset: Time) -> Self {
+    pub fn new(name: String, reward_frequency: TimeSpan, reward_offset: TimeSpan) -> Self {
         Self {
             name,
             reward_frequency,
             reward_offset,
+
+            river_yield: 1.0,
+            coast_yield: 1.0,
+            ideal_temperature: None,
+            temperature_tolerance: 20.0,
+            needed_precipitation: None,
+        }
+    }
+
+    /// Water and climate yields are optional.
+    pub fn from_data(entry: &DataEntry) -> CoreResult<Self> {
+        let mut sector_type = Self::new(
+            entry.get("name")?.as_str()?.to_string(),
+            entry.get("reward_frequency")?.as_time_span()?,
+            entry.get("reward_offset")?.as_time_span()?,
+        );
+
+        if let Some(river_yield_entry) = entry.get_optional("river_yield")? {
+            sector_type.river_yield = river_yield_entry.as_f64()? as f32;
+        }
+        if let Some(coast_yield_entry) = entry.get_optional("coast_yield")? {
+            sector_type.coast_yield = coast_yield_entry.as_f64()? as f32;
+        }
+        if let Some(temperature_entry) = entry.get_optional("ideal_temperature")? {
+            sector_type.ideal_temperature = Some(temperature_entry.as_f64()? as f32);
+        }
+        if let Some(tolerance_entry) = entry.get_optional("temperature_tolerance")? {
+            sector_type.temperature_tolerance = tolerance_entry.as_f64()? as f32;
+
+            if sector_type.temperature_tolerance <= 0.0 {
+                return Err(tolerance_entry.error("must be above 0"));
+            }
+        }
+        if let Some(precipitation_entry) = entry.get_optional("needed_precipitation")? {
+            sector_type.needed_precipitation = Some(precipitation_entry.as_f64()? as f32);
         }
+
+        Ok(sector_type)
     }
 
 
-    pub fn last_rewards(&self, current_time: Time) -> Time {
+    pub fn last_rewards(&self, current_time: GameDate) -> GameDate {
         let next_rewards = current_time + self.reward_offset;
 
         next_rewards - next_rewards % self.reward_frequency
     }
 
-    pub fn is_getting_rewards(&self, last_rewards: Time, current_time: Time) -> bool {
+    /// How much the tile's water and climate change rewards of this sector on it.
+    pub fn yield_multiplier(&self, tile: TileLink) -> f32 {
+        let mut multiplier = 1.0;
+        let climate = tile.climate();
+
+        if tile.water().is_river() {
+            multiplier *= self.river_yield;
+        }
+        if tile.water().is_coast() {
+            multiplier *= self.coast_yield;
+        }
+        if let Some(ideal_temperature) = self.ideal_temperature {
+            multiplier *= (1.0 - (climate.temperature - ideal_temperature).abs() / self.temperature_tolerance).max(0.0);
+        }
+        if let Some(needed_precipitation) = self.needed_precipitation {
+            if needed_precipitation > 0.0 {
+                multiplier *= (climate.precipitation / needed_precipitation).clamp(0.0, 1.0);
+            }
+        }
+
+        multiplier
+    }
+
+    pub fn is_getting_rewards(&self, last_rewards: GameDate, current_time: GameDate) -> bool {
         last_rewards + self.reward_frequency >= current_time
     }
 
+    /// Whether a reward date passed after `from`, up to and including `to`.
+    pub fn has_rewards_between(&self, from: GameDate, to: GameDate) -> bool {
+
+        self.last_rewards(from) != self.last_rewards(to)
+    }
+
+}
+
+
+
+/// Rewards of a single reward date of a sector type, summed for each tile owner.
+#[derive(Clone, Debug)]
+pub struct SectorHarvest {
+
+    pub date: GameDate,
+    /// Every land tile yields its `TileSectorType::yield_multiplier`, sea and lakes yield nothing.
+    pub owner_yields: BTreeMap<usize, f32>,
+
+}
+
+impl SectorHarvest {
+
+    pub fn collect(sector_type: &TileSectorType, date: GameDate, tiles: &TileChunks) -> Self {
+        let mut owner_yields = BTreeMap::new();
+
+        for tile in tiles {
+            if tile.water().is_open_water() {
+                continue;
+            }
+
+            *owner_yields.entry(tile.main.owner).or_insert(0.0) += sector_type.yield_multiplier(tile);
+        }
+
+        Self {
+            date,
+            owner_yields,
+        }
+    }
+
+    /// 0 for owners without any land.
+    pub fn owner_yield(&self, owner: usize) -> f32 {
+
+        self.owner_yields.get(&owner).copied().unwrap_or(0.0)
+    }
+
 }
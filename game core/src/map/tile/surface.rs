@@ -1,14 +1,20 @@
 use std::fs::File;
-use std::path::PathBuf;
-use crate::error::{CoreResult, OptionToCoreError, ResultToCoreError, CoreError};
+use std::path::{Path, PathBuf};
+use crate::data::DataEntry;
+use crate::data::pack::override_by_name;
+use crate::error::{CoreResult, OptionToCoreError, ResultToCoreError, CoreError, ValidationReport};
 use crate::image::color::{Rgb8, Rgba8};
-use crate::image::{ImageDimensions, Image, ImageResult};
-use crate::map::Map;
+use crate::image::{ImageDimensions, Image};
+use crate::map::{Map, MapShape};
+use crate::map::coordinates::{Direction, TileCoordinates};
+use crate::map::generator::hash_coordinates;
+use crate::map::tile::TileLink;
+use crate::map::tile::chunks::{ChunkCoordinates, TileChunks};
 
 
 
 /// Must be array of these. At length of layer number.
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct TileSurface {
 
     type_id: usize,
@@ -18,6 +24,12 @@ pub struct TileSurface {
 
 impl TileSurface {
 
+    /// Variant left for `SurfaceType::choose_variant` to pick, when the tile is drawn.
+    pub const AUTOMATIC_VARIANT: usize = usize::MAX;
+    /// Nothing in the layer, only allowed in layers with `empty = true`.
+    /// Its variant is automatic, so tiles given a type over it, without a variant, get one picked.
+    pub const EMPTY: TileSurface = TileSurface { type_id: usize::MAX, variant: Self::AUTOMATIC_VARIANT };
+
     pub fn new(type_id: usize, variant: usize) -> Self {
 
         Self {
@@ -26,6 +38,33 @@ impl TileSurface {
         }
     }
 
+    pub fn new_automatic(type_id: usize) -> Self {
+
+        Self::new(type_id, Self::AUTOMATIC_VARIANT)
+    }
+
+
+
+    pub fn type_id(&self) -> usize {
+
+        self.type_id
+    }
+
+    pub fn variant(&self) -> usize {
+
+        self.variant
+    }
+
+    pub fn is_empty(&self) -> bool {
+
+        self.type_id == Self::EMPTY.type_id
+    }
+
+    pub fn is_automatic(&self) -> bool {
+
+        self.variant == Self::AUTOMATIC_VARIANT
+    }
+
 }
 
 
@@ -52,22 +91,259 @@ impl SurfaceTypes {
         })
     }
 
-    pub fn build_surface_texture(&self, map: &Map) -> ImageResult<Image<Rgb8>> {
+    pub fn new_empty() -> Self {
+
+        Self {
+            tile_image_dimensions: ImageDimensions::ZERO,
+            layers: vec![],
+        }
+    }
+
+    /// Type directories default to `surface/{name}`, relative to given `directory`.
+    pub fn from_data(directory: &Path, entry: &DataEntry) -> CoreResult<Self> {
+        let mut surface_types = Self::new_empty();
+        surface_types.merge_data(directory, entry)?;
+
+        Ok(surface_types)
+    }
+
+    /// Layers are matched by their position, types inside of them by their names.
+    pub fn merge_data(&mut self, directory: &Path, entry: &DataEntry) -> CoreResult<()> {
+        let dimensions_entry = match entry.get_optional("tile_dimensions")? {
+            Some(dimensions_entry) => dimensions_entry,
+            None if self.tile_image_dimensions == ImageDimensions::ZERO => entry.get("tile_dimensions")?,
+            None => return self.merge_layers(directory, entry),
+        };
+        let dimensions = dimensions_entry.array()?;
+        if dimensions.len() != 2 {
+            return Err(dimensions_entry.error("must be `[width, height]`"));
+        }
+        self.tile_image_dimensions = ImageDimensions::new(dimensions[0].as_usize()?, dimensions[1].as_usize()?);
+
+        self.merge_layers(directory, entry)
+    }
+
+    fn merge_layers(&mut self, directory: &Path, entry: &DataEntry) -> CoreResult<()> {
+
+        for (layer_index, layer_entry) in entry.optional_array("layer")?.iter().enumerate() {
+            if layer_index == self.layers.len() {
+                self.layers.push(SurfaceTypeLayer { types: vec![], empty: false });
+            }
+
+            self.layers[layer_index].merge_data(directory, layer_entry)?;
+        }
+
+        Ok(())
+    }
+
+    /// Costs of every layer multiplied, `None` if any layer can't be entered.
+    /// Unknown types cost as much as plain ground, as validation reports them anyway.
+    pub fn movement_cost(&self, surfaces: &[TileSurface]) -> Option<f32> {
+        let mut movement_cost = 1.0;
+
+        for (layer, surface) in self.layers.iter().zip(surfaces) {
+            if let Some(surface_type) = layer.surface_type(surface.type_id) {
+                movement_cost *= surface_type.movement_cost()?;
+            }
+        }
+
+        Some(movement_cost)
+    }
+
+    /// Lowest cost any tile can have, for estimates that must never overshoot.
+    pub fn min_movement_cost(&self) -> f32 {
+
+        self.layers.iter()
+            .map(|layer| layer.types.iter().filter_map(SurfaceType::movement_cost).fold(f32::INFINITY, f32::min).min(1.0))
+            .product()
+    }
+
+    /// Every tile gets its own square of the texture, whatever the map shape is.
+    /// Hexagonal tiles are displayed from the hexagon inscribed in their square.
+    pub fn build_surface_texture(&self, map: &Map) -> CoreResult<Image<Rgb8>> {
         let mut surface_texture = Image::new_uniform(Rgb8::new(255, 255, 255), map.image_dimensions(self.tile_image_dimensions));
 
         for (tile_index, tile) in (&map.tiles).into_iter().enumerate() {
-            let tile_pos_tuple = map.properties.shape.coordinates(tile_index);
-            let tile_pos = ImageDimensions::from_u32_tuple(tile_pos_tuple);
-            let image_tile_pos = self.tile_image_dimensions * tile_pos;
+            let coordinates = map.properties.shape.coordinates(tile_index);
+            let image_tile_pos = self.tile_image_dimensions * ImageDimensions::from_u32_tuple(coordinates.to_tuple());
 
-            for layer in &self.layers {
-                surface_texture.overdraw_image(&layer.types[tile.surface[0].type_id].variants[tile.surface[0].variant], image_tile_pos)?;
-            }
+            self.draw_tile(map, &mut surface_texture, coordinates, tile, image_tile_pos)?;
+        }
+
+        Ok(surface_texture)
+    }
+
+    /// Part of `build_surface_texture` covering a single chunk, so changed chunks can be redrawn alone.
+    pub fn build_chunk_surface_texture(&self, map: &Map, chunk: ChunkCoordinates) -> CoreResult<Image<Rgb8>> {
+        let chunk = map.tiles.chunk(chunk)
+            .ok_or_else(|| CoreError::InvalidChunk { problem: format!("{chunk} is outside of the map") })?;
+        let first_tile = chunk.coordinates().first_tile();
+        let mut surface_texture = Image::new_uniform(
+            Rgb8::new(255, 255, 255),
+            self.tile_image_dimensions * ImageDimensions::from_u32_tuple(chunk.size()),
+        );
+
+        for (coordinates, tile) in chunk.tiles() {
+            let chunk_tile_pos = ImageDimensions::from_u32_tuple((coordinates.x - first_tile.x, coordinates.y - first_tile.y));
+
+            self.draw_tile(map, &mut surface_texture, coordinates, tile, self.tile_image_dimensions * chunk_tile_pos)?;
         }
 
         Ok(surface_texture)
     }
 
+    /// Layers are stacked in order, each blended over lower ones by its alpha, with its transitions on top.
+    fn draw_tile(&self, map: &Map, surface_texture: &mut Image<Rgb8>, coordinates: TileCoordinates, tile: TileLink, image_tile_pos: ImageDimensions) -> CoreResult<()> {
+        let tile_index = map.properties.shape.index(coordinates);
+
+        for ((layer_index, layer), surface) in self.layers.iter().enumerate().zip(tile.surface) {
+            let surface = layer.resolve_surface(surface, map.properties.seed, coordinates, layer_index);
+            if let Some(variant_image) = layer.variant_image(tile_index, layer_index, &surface)? {
+                surface_texture.overdraw_image(variant_image, image_tile_pos).to_core_error()?;
+            }
+
+            self.draw_transitions(map, surface_texture, coordinates, layer_index, &surface, image_tile_pos)?;
+        }
+
+        self.draw_water(map, surface_texture, coordinates, tile, image_tile_pos);
+
+        Ok(())
+    }
+
+    /// Neighbouring types of higher `transition_priority` spill over the tile's edges and corners, through their masks.
+    /// Corners are only drawn, when neither of the edges beside them already spills the same type.
+    /// Tiles of hexagonal maps don't line up with their squares, so they get no transitions.
+    fn draw_transitions(&self, map: &Map, surface_texture: &mut Image<Rgb8>, coordinates: TileCoordinates, layer_index: usize, surface: &TileSurface, image_tile_pos: ImageDimensions) -> CoreResult<()> {
+        if let MapShape::Hexagonal { .. } = map.properties.shape {
+            return Ok(())
+        }
+        let layer = &self.layers[layer_index];
+        let neighbour_surface = |direction: Direction| {
+            let neighbour_coordinates = map.properties.shape.neighbour(coordinates, direction)?;
+            let neighbour_surface = map.tiles.get_at(neighbour_coordinates)?.surface.get(layer_index)?;
+
+            Some(layer.resolve_surface(neighbour_surface, map.properties.seed, neighbour_coordinates, layer_index))
+        };
+
+        // Empty surfaces, having no type, rank below any other.
+        let own_rank = layer.transition_rank(surface);
+        let mut spilling_surfaces = vec![];
+        for direction in Direction::ALL {
+            let Some(neighbour) = neighbour_surface(direction) else { continue };
+            let neighbour_rank = layer.transition_rank(&neighbour);
+            if neighbour_rank <= own_rank {
+                continue;
+            }
+
+            let covered_by_edge = direction.components().is_some_and(|(first_edge, second_edge)| {
+                [first_edge, second_edge].into_iter().any(|edge| neighbour_surface(edge).is_some_and(|edge_surface| edge_surface.type_id == neighbour.type_id))
+            });
+            if !covered_by_edge {
+                spilling_surfaces.push((neighbour_rank, direction, neighbour));
+            }
+        }
+        spilling_surfaces.sort_by_key(|(rank, _, _)| *rank);
+
+        for (_, direction, neighbour) in spilling_surfaces {
+            let Some(surface_type) = layer.surface_type(neighbour.type_id) else { continue };
+            let (Some(masks), Some(variant_image)) = (&surface_type.masks, surface_type.variant(neighbour.variant)) else { continue };
+
+            surface_texture.overdraw_with_shaped_image(variant_image, image_tile_pos, masks.mask(direction), TransitionMasks::SHAPE_COLOR).to_core_error()?;
+        }
+
+        Ok(())
+    }
+
+    const SEA_COLOR: Rgb8 = Rgb8::new(36, 78, 134);
+    const FRESH_WATER_COLOR: Rgb8 = Rgb8::new(58, 112, 170);
+
+    /// Sea and lakes cover the whole tile, rivers run from the tile center towards neighbouring water.
+    /// Rivers of hexagonal maps run to other rows straight up, or down, see `MapShape::image_side`.
+    fn draw_water(&self, map: &Map, surface_texture: &mut Image<Rgb8>, coordinates: TileCoordinates, tile: TileLink, image_tile_pos: ImageDimensions) {
+        let water = tile.water();
+        let tile_size = self.tile_image_dimensions;
+
+        if water.is_sea() {
+            surface_texture.fill_rectangle(Self::SEA_COLOR, image_tile_pos, tile_size);
+        } else if water.is_lake() {
+            surface_texture.fill_rectangle(Self::FRESH_WATER_COLOR, image_tile_pos, tile_size);
+        } else if water.is_river() {
+            let river_width = ImageDimensions::new((tile_size.x / 4).max(1), (tile_size.y / 4).max(1));
+            let river_offset = ImageDimensions::new((tile_size.x - river_width.x) / 2, (tile_size.y - river_width.y) / 2);
+
+            surface_texture.fill_rectangle(Self::FRESH_WATER_COLOR, image_tile_pos + river_offset, river_width);
+
+            for (neighbour, neighbour_tile) in map.tiles.neighbours(coordinates) {
+                if !(neighbour_tile.water().is_river() || neighbour_tile.water().is_open_water()) {
+                    continue;
+                }
+
+                let (band_offset, band_size) = match map.properties.shape.image_side(coordinates, neighbour) {
+                    (_, 1) => (ImageDimensions::new(river_offset.x, river_offset.y), ImageDimensions::new(river_width.x, tile_size.y - river_offset.y)),
+                    (_, -1) => (ImageDimensions::new(river_offset.x, 0), ImageDimensions::new(river_width.x, river_offset.y + river_width.y)),
+                    (1, _) => (ImageDimensions::new(river_offset.x, river_offset.y), ImageDimensions::new(tile_size.x - river_offset.x, river_width.y)),
+                    _ => (ImageDimensions::new(0, river_offset.y), ImageDimensions::new(river_offset.x + river_width.x, river_width.y)),
+                };
+
+                surface_texture.fill_rectangle(Self::FRESH_WATER_COLOR, image_tile_pos + band_offset, band_size);
+            }
+        }
+    }
+
+
+
+    /// Reports variant images, that failed to load, or have other dimensions than tiles.
+    pub fn validate(&self, report: &mut ValidationReport) {
+
+        for layer in &self.layers {
+            for surface_type in &layer.types {
+                for variant in surface_type.variants.iter().chain(&surface_type.mask_files) {
+                    match &variant.image {
+                        Ok(image) => if image.dimensions() != self.tile_image_dimensions {
+                            report.push(CoreError::TileDimensionsDontMatch {
+                                path: variant.path.clone(),
+                                expected: self.tile_image_dimensions,
+                                found: image.dimensions(),
+                            });
+                        },
+                        Err(problem) => report.push(CoreError::MissingVariantImage {
+                            path: variant.path.clone(),
+                            problem: problem.clone(),
+                        }),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fails for surfaces, that don't exist in the layer, including empty ones in layers that can't be empty.
+    /// Automatic variants are checked as resolved for the tile at `coordinates`, with the map `seed`.
+    pub fn check_surface(&self, tile_index: usize, layer_index: usize, surface: &TileSurface, seed: u64, coordinates: TileCoordinates) -> CoreResult<()> {
+
+        self.layers.get(layer_index)
+            .to_core_error(CoreError::UnknownSurfaceLayer { tile_index, layer: layer_index })?
+            .check_surface(tile_index, layer_index, surface, seed, coordinates)
+    }
+
+    /// Reports tile surfaces, which point to surface types, or variants, that don't exist.
+    /// Automatic variants are resolved with the map `seed`, see `check_surface`.
+    pub fn validate_tiles(&self, tiles: &TileChunks, seed: u64, report: &mut ValidationReport) {
+        let shape = tiles.shape();
+
+        for (tile_index, tile) in tiles.into_iter().enumerate() {
+            let coordinates = shape.coordinates(tile_index);
+
+            for (layer_index, layer) in self.layers.iter().enumerate() {
+                match tile.surface.get(layer_index) {
+                    Some(surface) => if let Err(problem) = layer.check_surface(tile_index, layer_index, surface, seed, coordinates) {
+                        report.push(problem);
+                    },
+                    None => report.push(CoreError::UnknownSurfaceLayer { tile_index, layer: layer_index }),
+                }
+            }
+        }
+    }
+
 }
 
 
@@ -75,6 +351,8 @@ impl SurfaceTypes {
 pub struct SurfaceTypeLayer {
 
     types: Vec<SurfaceType>,
+    /// Whether tiles can have nothing in this layer, like overlays of forests and roads.
+    empty: bool,
 
 }
 
@@ -89,9 +367,81 @@ impl SurfaceTypeLayer {
 
         Ok(Self {
             types,
+            empty: false,
         })
     }
 
+    /// Name standing for `TileSurface::EMPTY` in data, in layers that can be empty.
+    pub const EMPTY_NAME: &'static str = "empty";
+
+    pub fn merge_data(&mut self, directory: &Path, entry: &DataEntry) -> CoreResult<()> {
+        if let Some(empty_entry) = entry.get_optional("empty")? {
+            self.empty = empty_entry.as_bool()?;
+        }
+
+        for type_entry in entry.optional_array("type")? {
+            override_by_name(&mut self.types, SurfaceType::from_data(directory, &type_entry)?, |surface_type| &surface_type.name_id);
+        }
+
+        Ok(())
+    }
+
+
+
+    /// `EMPTY_NAME` gives the type id of `TileSurface::EMPTY`, if the layer can be empty.
+    pub fn type_id(&self, name_id: &str) -> Option<usize> {
+
+        self.types.iter().position(|surface_type| surface_type.name_id == name_id)
+            .or_else(|| (self.empty && name_id == Self::EMPTY_NAME).then_some(TileSurface::EMPTY.type_id))
+    }
+
+    pub fn can_be_empty(&self) -> bool {
+
+        self.empty
+    }
+
+    /// Order in which types spill over each other, `None` for empty, or unknown surfaces.
+    fn transition_rank(&self, surface: &TileSurface) -> Option<(i32, usize)> {
+
+        Some((self.surface_type(surface.type_id)?.transition_priority, surface.type_id))
+    }
+
+    pub fn surface_type(&self, type_id: usize) -> Option<&SurfaceType> {
+
+        self.types.get(type_id)
+    }
+
+    /// Automatic variants are picked by `SurfaceType::choose_variant`, other surfaces are kept as they are.
+    pub fn resolve_surface(&self, surface: &TileSurface, seed: u64, coordinates: TileCoordinates, layer_index: usize) -> TileSurface {
+
+        match self.surface_type(surface.type_id) {
+            Some(surface_type) if surface.is_automatic() => TileSurface::new(surface.type_id, surface_type.choose_variant(seed, coordinates, layer_index)),
+            _ => surface.clone(),
+        }
+    }
+
+    fn check_surface(&self, tile_index: usize, layer_index: usize, surface: &TileSurface, seed: u64, coordinates: TileCoordinates) -> CoreResult<()> {
+
+        self.variant_image(tile_index, layer_index, &self.resolve_surface(surface, seed, coordinates, layer_index))
+            .map(|_| ())
+    }
+
+    /// `None` for empty surfaces, in layers that can be empty.
+    fn variant_image(&self, tile_index: usize, layer_index: usize, surface: &TileSurface) -> CoreResult<Option<&Image<Rgba8>>> {
+        if surface.is_empty() {
+            return if self.empty { Ok(None) } else { Err(CoreError::EmptySurface { tile_index, layer: layer_index }) }
+        }
+        let surface_type = self.types.get(surface.type_id)
+            .to_core_error(CoreError::UnknownSurfaceType { tile_index, layer: layer_index, type_id: surface.type_id })?;
+
+        surface_type.variant(surface.variant).to_core_error(CoreError::UnknownSurfaceVariant {
+            tile_index,
+            layer: layer_index,
+            type_name: surface_type.name_id.clone(),
+            variant: surface.variant,
+        }).map(Some)
+    }
+
 }
 
 
@@ -99,7 +449,19 @@ impl SurfaceTypeLayer {
 pub struct SurfaceType {
 
     name_id: String,
-    variants: Vec<Image<Rgba8>>,
+    variants: Vec<SurfaceVariant>,
+    /// How often `choose_variant` picks each variant, relative to others, one for each variant.
+    variant_weights: Vec<u32>,
+
+    /// Multiplies the cost of moving onto tiles of this type, `None` if they can't be entered at all.
+    movement_cost: Option<f32>,
+
+    /// Types spill over neighbouring tiles of types with lower priority, ties go to the higher type id.
+    transition_priority: i32,
+    /// Only types with both mask files spill over their neighbours.
+    masks: Option<TransitionMasks>,
+    /// Kept for `SurfaceTypes::validate` to report, like variants.
+    mask_files: Vec<SurfaceVariant>,
 
 }
 
@@ -108,22 +470,255 @@ impl SurfaceType {
     pub fn new(directory_path: PathBuf, variant_amount: usize) -> CoreResult<Self> {
         let name_id = directory_path.file_name().to_core_error(CoreError::InvalidPath)?.to_string_lossy().into();
 
+        Ok(Self::load(name_id, directory_path, variant_amount))
+    }
+
+    pub fn from_data(directory: &Path, entry: &DataEntry) -> CoreResult<Self> {
+        let name_id = entry.get("name")?.as_str()?.to_string();
+        let type_directory = match entry.get_optional("directory")? {
+            Some(directory_entry) => directory.join(directory_entry.as_str()?),
+            None => directory.join("surface").join(&name_id),
+        };
+
+        let mut surface_type = Self::load(name_id, type_directory, entry.get("variants")?.as_usize()?);
+
+        if let Some(cost_entry) = entry.get_optional("movement_cost")? {
+            let movement_cost = cost_entry.as_f64()? as f32;
+            if movement_cost < 0.0 {
+                return Err(cost_entry.error("must not be negative"));
+            }
+
+            surface_type.movement_cost = Some(movement_cost);
+        }
+        if let Some(passable_entry) = entry.get_optional("passable")? {
+            if !passable_entry.as_bool()? {
+                surface_type.movement_cost = None;
+            }
+        }
+        if let Some(priority_entry) = entry.get_optional("transition_priority")? {
+            surface_type.transition_priority = priority_entry.as_i32()?;
+        }
+        if let Some(weights_entry) = entry.get_optional("weights")? {
+            let weights = weights_entry.array()?.iter().map(DataEntry::as_u32).collect::<CoreResult<Vec<u32>>>()?;
+            if weights.len() != surface_type.variant_amount() {
+                return Err(weights_entry.error(format!("must have a weight for each of {} variants", surface_type.variant_amount())));
+            }
+            if !weights.is_empty() && weights.iter().all(|&weight| weight == 0) {
+                return Err(weights_entry.error("must have a weight above 0"));
+            }
+
+            surface_type.variant_weights = weights;
+        }
+
+        Ok(surface_type)
+    }
+
+    /// Variants, that fail to load, are kept for `SurfaceTypes::validate` to report.
+    /// Transition masks are optional, but if one of them is there, both must be.
+    pub fn load(name_id: String, directory_path: PathBuf, variant_amount: usize) -> Self {
         let mut variants = vec![];
         for variant_index in 0..variant_amount {
             let mut variant_image_path = directory_path.clone();
-            variant_image_path.push(&format!("{variant_index}.png"));
-
-            let image_reader = File::open(variant_image_path).to_core_error()?;
-            let mut image = Image::load_png(image_reader).to_core_error()?;
-            image.invert_on_y();
+            variant_image_path.push(format!("{variant_index}.png"));
 
-            variants.push(image);
+            variants.push(SurfaceVariant::load(variant_image_path));
         }
 
-        Ok(Self {
+        let mask_paths = [TransitionMasks::EDGE_FILE, TransitionMasks::CORNER_FILE].map(|file_name| directory_path.join(file_name));
+        let mask_files: Vec<SurfaceVariant> = if mask_paths.iter().any(|path| path.exists()) {
+            mask_paths.into_iter().map(SurfaceVariant::load).collect()
+        } else {
+            vec![]
+        };
+        let masks = match mask_files.as_slice() {
+            [SurfaceVariant { image: Ok(edge), .. }, SurfaceVariant { image: Ok(corner), .. }] => Some(TransitionMasks::new(edge, corner)),
+            _ => None,
+        };
+
+        Self {
             name_id,
+            variant_weights: vec![1; variants.len()],
             variants,
-        })
+
+            movement_cost: Some(1.0),
+
+            transition_priority: 0,
+            masks,
+            mask_files,
+        }
+    }
+
+
+
+    pub fn name_id(&self) -> &str {
+
+        &self.name_id
+    }
+
+    pub fn variant(&self, variant: usize) -> Option<&Image<Rgba8>> {
+
+        self.variants.get(variant)?.image.as_ref().ok()
+    }
+
+    pub fn variant_amount(&self) -> usize {
+
+        self.variants.len()
+    }
+
+    pub fn variant_weight(&self, variant: usize) -> Option<u32> {
+
+        self.variant_weights.get(variant).copied()
+    }
+
+    /// Weighted pick, always the same for the same seed, tile and layer, so it needs no storing.
+    /// Types without variants get variant 0, which validation reports as missing.
+    pub fn choose_variant(&self, seed: u64, coordinates: TileCoordinates, layer_index: usize) -> usize {
+        let total_weight: u64 = self.variant_weights.iter().map(|&weight| weight as u64).sum();
+        if total_weight == 0 {
+            return 0;
+        }
+
+        let mut remaining = hash_coordinates(seed, coordinates.x as i64, coordinates.y as i64, layer_index as u64) % total_weight;
+        for (variant, &weight) in self.variant_weights.iter().enumerate() {
+            if remaining < weight as u64 {
+                return variant;
+            }
+            remaining -= weight as u64;
+        }
+
+        0
+    }
+
+    pub fn movement_cost(&self) -> Option<f32> {
+
+        self.movement_cost
+    }
+
+    pub fn transition_priority(&self) -> i32 {
+
+        self.transition_priority
+    }
+
+    pub fn has_transitions(&self) -> bool {
+
+        self.masks.is_some()
+    }
+
+}
+
+
+
+/// Shapes of a type spilling over the edges and corners of neighbouring tiles, white where it's drawn.
+/// Only the north edge and the north east corner are loaded, others are mirrored from them.
+struct TransitionMasks {
+
+    /// Following `Direction::ALL`.
+    masks: [Image<Rgba8>; 8],
+
+}
+
+impl TransitionMasks {
+
+    const EDGE_FILE: &'static str = "edge.png";
+    const CORNER_FILE: &'static str = "corner.png";
+    const SHAPE_COLOR: Rgba8 = Rgba8::new(255, 255, 255, 255);
+
+    fn new(north_edge: &Image<Rgba8>, north_east_corner: &Image<Rgba8>) -> Self {
+        let mirrored = |image: &Image<Rgba8>, on_x: bool, on_y: bool| {
+            let mut mirrored_image = image.clone();
+            if on_x {
+                mirrored_image.invert_on_x();
+            }
+            if on_y {
+                mirrored_image.invert_on_y();
+            }
+
+            mirrored_image
+        };
+        let mut east_edge = north_edge.clone();
+        east_edge.transpose();
+        let west_edge = mirrored(&east_edge, true, false);
+
+        Self {
+            masks: [
+                north_edge.clone(),
+                north_east_corner.clone(),
+                east_edge,
+                mirrored(north_east_corner, false, true),
+                mirrored(north_edge, false, true),
+                mirrored(north_east_corner, true, true),
+                west_edge,
+                mirrored(north_east_corner, true, false),
+            ],
+        }
+    }
+
+    fn mask(&self, direction: Direction) -> &Image<Rgba8> {
+
+        &self.masks[Direction::ALL.iter().position(|&mask_direction| mask_direction == direction).unwrap_or(0)]
+    }
+
+}
+
+
+
+struct SurfaceVariant {
+
+    path: PathBuf,
+    image: Result<Image<Rgba8>, String>,
+
+}
+
+impl SurfaceVariant {
+
+    fn load(path: PathBuf) -> Self {
+        let image: CoreResult<Image<Rgba8>> = File::open(&path).to_core_error()
+            .and_then(|image_reader| Image::load_png(image_reader).to_core_error())
+            .map(|mut image| {
+                image.invert_on_y();
+                image
+            });
+
+        Self {
+            path,
+            image: image.map_err(|error| error.to_string()),
+        }
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use crate::Definitions;
+    use super::*;
+
+    #[test]
+    fn surfaces_are_checked_at_their_tile() {
+        let definitions = Definitions::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../game sets/historical")).unwrap();
+        let surface_types = &definitions.surface_types;
+        let far_tile = TileCoordinates::new(517, 93);
+
+        // Plains have 4 variants.
+        assert!(surface_types.check_surface(0, 0, &TileSurface::new(0, 3), 7, far_tile).is_ok());
+        assert!(matches!(
+            surface_types.check_surface(9, 0, &TileSurface::new(0, 4), 7, far_tile),
+            Err(CoreError::UnknownSurfaceVariant { tile_index: 9, layer: 0, variant: 4, .. }),
+        ));
+
+        for (seed, x, y) in [(0, 0, 0), (7, 517, 93), (u64::MAX, 1, 40_000)] {
+            let coordinates = TileCoordinates::new(x, y);
+            let automatic = TileSurface::new_automatic(0);
+
+            assert!(surface_types.check_surface(0, 0, &automatic, seed, coordinates).is_ok());
+            assert!(surface_types.layers[0].resolve_surface(&automatic, seed, coordinates, 0).variant() < 4);
+        }
+
+        assert!(matches!(surface_types.check_surface(0, 5, &TileSurface::new(0, 0), 0, far_tile), Err(CoreError::UnknownSurfaceLayer { layer: 5, .. })));
+        assert!(matches!(surface_types.check_surface(0, 0, &TileSurface::EMPTY, 0, far_tile), Err(CoreError::EmptySurface { .. })));
+        assert!(surface_types.check_surface(0, 1, &TileSurface::EMPTY, 0, far_tile).is_ok());
     }
 
 }
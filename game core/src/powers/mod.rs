@@ -1,64 +1,55 @@
+use crate::image::Image;
+use crate::image::color::Rgb8;
+use crate::map::generator::hash_coordinates;
+
+
+
 /// Powers are countries, it's institutions, factions and corporations.
 pub struct Power {
 
     name: String,
+    /// Fills the power's territory on political maps.
+    color: Rgb8,
+    /// Drawn over the power's territory on political maps, flipped like textures, with the bottom row first.
+    flag: Option<Image<Rgb8>>,
 
 }
 
 impl Power {
 
-    pub fn new(name: String) -> Self {
+    pub fn new(name: String, color: Rgb8, flag: Option<Image<Rgb8>>) -> Self {
 
         Self {
             name,
+            color,
+            flag,
         }
     }
 
+    /// Color picked from the name, for powers without one given.
+    pub fn name_color(name: &str) -> Rgb8 {
+        let name_hash = name.bytes().fold(0, |hash, byte| hash_coordinates(hash, byte as i64, 0, 0));
+        let [r, g, b, ..] = name_hash.to_le_bytes();
 
+        // Kept away from black and white, so borders and flags stay visible.
+        Rgb8::new(r / 2 + 64, g / 2 + 64, b / 2 + 64)
+    }
 
-    pub fn icon(&self) -> (Vec<u8>, usize, usize) {
-
-        match self.name.as_str() {
-            "Poland" => {
-                let mut image = vec![];
-
-                for _ in 0..5 * 16 * 3 {
-                    image.push(255);
-                }
-                for _ in 0..5 * 16 {
-                    image.push(255);
-                    image.push(0);
-                    image.push(0);
-                }
-
-                (image, 16, 10)
-            },
-            "Ukraine" => {
-                let mut image = vec![];
-
-                for _ in 0..5 * 16 {
-                    image.push(64);
-                    image.push(64);
-                    image.push(255);
-                }
-                for _ in 0..5 * 16 {
-                    image.push(255);
-                    image.push(255);
-                    image.push(64);
-                }
-                (image, 16, 10)
-            },
-            "Hungary" => {
-                let image = vec![
-                    223,  16,  16, 223,  16,  16, 233,  16,  16, 223,  16,  16, 223,  16,  16, 233,  16,  16, 223,  16,  16, 223,  16,  16, 233,  16,  16,
-                    255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
-                    16,  223,  16,  16, 223,  16,  16, 223,  16,  16, 223,  16,  16, 223,  16,  16, 223,  16,  16, 223,  16,  16, 223,  16,  16, 223,  16,
-                ];
-
-                (image, 6, 3)
-            },
-            _ => (vec![0], 1, 1),
-        }
+
+
+    pub fn name(&self) -> &str {
+
+        &self.name
+    }
+
+    pub fn color(&self) -> Rgb8 {
+
+        self.color
+    }
+
+    pub fn flag(&self) -> Option<&Image<Rgb8>> {
+
+        self.flag.as_ref()
     }
 
 }
@@ -0,0 +1,322 @@
+use std::path::{Path, PathBuf};
+use crate::Definitions;
+use crate::data::{DataEntry, DataFile};
+use crate::error::CoreResult;
+use crate::image::color::Rgb8;
+use crate::map::MapSettings;
+use crate::map::import::{load_image_file, MapImages};
+use crate::map::provinces::Provinces;
+use crate::map::tile::TileLocal;
+use crate::map::tile::surface::TileSurface;
+use crate::map::units::TerrainHeight;
+use crate::powers::Power;
+use crate::units::GameDate;
+
+
+
+/// Starting situation of a game, with every tile already resolved against `Definitions`.
+pub struct Scenario {
+
+    pub name: String,
+    pub start_date: GameDate,
+    pub powers: Vec<Power>,
+
+    pub map_settings: MapSettings,
+    pub tiles: Vec<TileLocal>,
+    pub provinces: Provinces,
+
+}
+
+impl Scenario {
+
+    pub const DEFAULT_PATH: &'static str = "game sets/historical/scenarios/1918.toml";
+
+    pub fn load(path: PathBuf, definitions: &Definitions) -> CoreResult<Self> {
+        let file = DataFile::load(path)?;
+
+        Self::from_data(&file.root(), definitions)
+    }
+
+    pub fn from_data(entry: &DataEntry, definitions: &Definitions) -> CoreResult<Self> {
+        let name = entry.get("name")?.as_str()?.to_string();
+        let start_date = entry.get("start")?.as_date()?;
+        let directory = entry.file().parent().unwrap_or(Path::new(""));
+
+        let mut powers = vec![];
+        for power_entry in entry.get("power")?.array()? {
+            let name = power_entry.get("name")?.as_str()?.to_string();
+            let color = match power_entry.get_optional("color")? {
+                Some(color_entry) => {
+                    let color_text = color_entry.as_str()?;
+                    Rgb8::from_hex(color_text).ok_or_else(|| color_entry.error(format!("`{color_text}` isn't a color like `#ff8000`")))?
+                },
+                None => Power::name_color(&name),
+            };
+            let flag = match power_entry.get_optional("flag")? {
+                Some(flag_entry) => Some(load_image_file(&directory.join(flag_entry.as_str()?))?),
+                None => None,
+            };
+
+            powers.push(Power::new(name, color, flag));
+        }
+
+        let map_settings = MapSettings::from_data(&entry.get("map")?, definitions)?;
+        let tiles = ScenarioTiles::new(&entry.get("tiles")?, definitions, &powers, &map_settings)?.tiles;
+        let provinces = match entry.get_optional("provinces")? {
+            Some(provinces_entry) => Provinces::from_data(&provinces_entry, directory, &map_settings)?,
+            None => Provinces::new_empty(map_settings.shape),
+        };
+
+        Ok(Self {
+            name,
+            start_date,
+            powers,
+
+            map_settings,
+            tiles,
+            provinces,
+        })
+    }
+
+}
+
+
+
+/// Fills tiles from defaults first, then from the map generator and images if there are any,
+/// then from every per tile row layer present, with water and climate derived from the final heights.
+struct ScenarioTiles<'a> {
+
+    map_settings: &'a MapSettings,
+    tiles: Vec<TileLocal>,
+
+}
+
+impl<'a> ScenarioTiles<'a> {
+
+    /// Stands for `TileSurface::AUTOMATIC_VARIANT` in variant rows.
+    const AUTOMATIC_VARIANT: &'static str = "*";
+
+    fn new(entry: &DataEntry, definitions: &Definitions, powers: &[Power], map_settings: &'a MapSettings) -> CoreResult<Self> {
+        let default_owner = Self::power_index(&entry.get("default_owner")?, powers)?;
+
+        let default_surface_entries = entry.get("default_surface")?;
+        let default_surface_list = default_surface_entries.array()?;
+        let layers = &definitions.surface_types.layers;
+        if default_surface_list.len() != layers.len() {
+            return Err(default_surface_entries.error(format!("must have one surface for each of {} layers", layers.len())));
+        }
+
+        let mut default_surface = vec![];
+        for (layer_index, surface_entry) in default_surface_list.iter().enumerate() {
+            let type_entry = surface_entry.get("type")?;
+            let type_id = Self::surface_type_id(&type_entry, definitions, layer_index, type_entry.as_str()?)?;
+
+            default_surface.push(match surface_entry.get_optional("variant")? {
+                Some(variant_entry) => TileSurface::new(type_id, variant_entry.as_usize()?),
+                None => TileSurface::new_automatic(type_id),
+            });
+        }
+
+        let mut default_tile = TileLocal::new(default_owner, default_surface.into_boxed_slice());
+        if let Some(height_entry) = entry.get_optional("default_height")? {
+            default_tile.main.height = TerrainHeight::from_meters(height_entry.as_i32()?);
+        }
+
+        let mut scenario_tiles = Self {
+            map_settings,
+            tiles: vec![default_tile; map_settings.shape.tile_amount() as usize],
+        };
+
+        // The generator derives water and climate itself, anything changing heights after it makes them outdated.
+        let mut environment_outdated = true;
+        if let Some(generator) = &map_settings.generator {
+            generator.generate(map_settings, definitions, &mut scenario_tiles.tiles);
+            environment_outdated = false;
+        }
+
+        if let Some(images_entry) = entry.get_optional("images")? {
+            let directory = images_entry.file().parent().unwrap_or(Path::new(""));
+
+            MapImages::from_data(&images_entry, directory, definitions, powers)?
+                .apply(map_settings, &mut scenario_tiles.tiles)?;
+            environment_outdated = true;
+        }
+
+        if let Some(owner_rows) = entry.get_optional("owner")? {
+            let owner_key = entry.get("owner_key")?;
+
+            scenario_tiles.read_rows(&owner_rows, |tile, tile_entry| {
+                let power_name = owner_key.get(&tile_entry)?;
+
+                tile.main.owner = Self::power_index(&power_name, powers)?;
+                Ok(())
+            })?;
+        }
+
+        if let Some(height_rows) = entry.get_optional("height")? {
+            scenario_tiles.read_integer_rows(&height_rows, |tile, height_entry| {
+                tile.main.height = TerrainHeight::from_meters(height_entry.as_i32()?);
+                Ok(())
+            })?;
+            environment_outdated = true;
+        }
+
+        for (layer_index, layer_entry) in entry.optional_array("surface")?.iter().enumerate() {
+            if layer_index >= layers.len() {
+                return Err(layer_entry.error(format!("there are only {} surface layers", layers.len())));
+            }
+
+            if let Some(type_rows) = layer_entry.get_optional("types")? {
+                let type_key = layer_entry.get("key")?;
+
+                scenario_tiles.read_rows(&type_rows, |tile, tile_entry| {
+                    let type_entry = type_key.get(&tile_entry)?;
+                    let type_id = Self::surface_type_id(&type_entry, definitions, layer_index, type_entry.as_str()?)?;
+
+                    // Variants of the previous type may not exist for the new one, so it gets picked again, unless forced by variant rows.
+                    if tile.surface[layer_index].type_id() != type_id {
+                        tile.surface[layer_index] = TileSurface::new_automatic(type_id);
+                    }
+                    Ok(())
+                })?;
+            }
+
+            // One character per tile, like other rows, so only variants 0 to 9 can be forced.
+            if let Some(variant_rows) = layer_entry.get_optional("variants")? {
+                scenario_tiles.read_rows(&variant_rows, |tile, tile_entry| {
+                    let variant = match tile_entry.as_str() {
+                        Self::AUTOMATIC_VARIANT => TileSurface::AUTOMATIC_VARIANT,
+                        _ => tile_entry.parse().map_err(|_| variant_rows.error(format!("`{tile_entry}` isn't a variant digit, or `{}`", Self::AUTOMATIC_VARIANT)))?,
+                    };
+
+                    tile.surface[layer_index] = TileSurface::new(tile.surface[layer_index].type_id(), variant);
+                    Ok(())
+                })?;
+            }
+        }
+
+        if environment_outdated {
+            map_settings.apply_environment(&mut scenario_tiles.tiles);
+        }
+
+        Ok(scenario_tiles)
+    }
+
+
+
+    /// Rows of strings, where every character stands for a single tile.
+    fn read_rows(&mut self, rows_entry: &DataEntry, mut apply: impl FnMut(&mut TileLocal, String) -> CoreResult<()>) -> CoreResult<()> {
+
+        for (tile_y, row_entry) in self.checked_rows(rows_entry)?.into_iter().enumerate() {
+            let row = row_entry.as_str()?;
+            self.check_row_length(&row_entry, row.chars().count())?;
+
+            for (tile_x, tile_character) in row.chars().enumerate() {
+                let tile_index = self.map_settings.shape.raw_index(tile_x as u32, tile_y as u32);
+
+                apply(&mut self.tiles[tile_index], tile_character.to_string())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_integer_rows(&mut self, rows_entry: &DataEntry, mut apply: impl FnMut(&mut TileLocal, DataEntry) -> CoreResult<()>) -> CoreResult<()> {
+
+        for (tile_y, row_entry) in self.checked_rows(rows_entry)?.into_iter().enumerate() {
+            let row = row_entry.array()?;
+            self.check_row_length(&row_entry, row.len())?;
+
+            for (tile_x, tile_entry) in row.into_iter().enumerate() {
+                let tile_index = self.map_settings.shape.raw_index(tile_x as u32, tile_y as u32);
+
+                apply(&mut self.tiles[tile_index], tile_entry)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn checked_rows<'b>(&self, rows_entry: &DataEntry<'b>) -> CoreResult<Vec<DataEntry<'b>>> {
+        let rows = rows_entry.array()?;
+        let (_, height) = self.map_settings.shape.max_axis();
+
+        if rows.len() != height as usize {
+            return Err(rows_entry.error(format!("must have {height} rows, has {}", rows.len())));
+        }
+
+        Ok(rows)
+    }
+
+    fn check_row_length(&self, row_entry: &DataEntry, length: usize) -> CoreResult<()> {
+        let (width, _) = self.map_settings.shape.max_axis();
+
+        if length != width as usize {
+            return Err(row_entry.error(format!("must be {width} tiles long, is {length}")));
+        }
+
+        Ok(())
+    }
+
+
+
+    fn power_index(name_entry: &DataEntry, powers: &[Power]) -> CoreResult<usize> {
+        let name = name_entry.as_str()?;
+
+        powers.iter().position(|power| power.name() == name)
+            .ok_or_else(|| name_entry.error(format!("names unknown power `{name}`")))
+    }
+
+    fn surface_type_id(type_entry: &DataEntry, definitions: &Definitions, layer_index: usize, name: &str) -> CoreResult<usize> {
+
+        definitions.surface_types.layers[layer_index].type_id(name)
+            .ok_or_else(|| type_entry.error(format!("names unknown surface type `{name}` in layer {layer_index}")))
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use crate::data::DataFile;
+    use super::*;
+
+    fn load_surfaces(definitions: &Definitions, rows: &str) -> Vec<TileSurface> {
+        let text = format!(r#"
+            name = "Test"
+            start = "1918-01-01"
+            [[power]]
+            name = "Test Power"
+            [map]
+            tile_size = 1000
+            shape = "rectangular"
+            width = 3
+            height = 1
+            [tiles]
+            default_owner = "Test Power"
+            default_surface = [{{ type = "plains", variant = 3 }}, {{ type = "empty" }}]
+            [[tiles.surface]]
+            key = {{ p = "plains", d = "dessert" }}
+            {rows}
+        "#);
+        let file = DataFile::parse(PathBuf::from("test.toml"), &text).unwrap();
+        let scenario = Scenario::from_data(&file.root(), definitions).unwrap();
+
+        for (tile_index, tile) in scenario.tiles.iter().enumerate() {
+            definitions.surface_types.check_surface(tile_index, 0, &tile.surface[0], scenario.map_settings.seed, scenario.map_settings.shape.coordinates(tile_index)).unwrap();
+        }
+        scenario.tiles.iter().map(|tile| tile.surface[0].clone()).collect()
+    }
+
+    #[test]
+    fn type_rows_pick_variants_again() {
+        let definitions = Definitions::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../game sets/historical")).unwrap();
+
+        // Dessert has just a single variant, so keeping variant 3 of plains would be invalid, unchanged types keep theirs.
+        assert_eq!(load_surfaces(&definitions, r#"types = ["pdd"]"#), vec![TileSurface::new(0, 3), TileSurface::new_automatic(1), TileSurface::new_automatic(1)]);
+        assert_eq!(load_surfaces(&definitions, "types = [\"pdd\"]\nvariants = [\"1*0\"]"), vec![TileSurface::new(0, 1), TileSurface::new_automatic(1), TileSurface::new(1, 0)]);
+    }
+
+}
@@ -0,0 +1,302 @@
+use std::fmt::{Display as FmtDisplay, Formatter};
+use std::str::FromStr;
+use crate::error::CoreError;
+use crate::units::{GameDate, TimeSpan};
+
+
+
+/// Date in proleptic Gregorian calendar.
+/// `GameDate::EPOCH` is midnight of the 1st of January, year 0.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct CalendarDate {
+
+    pub year: i64,
+    /// From 1 to 12.
+    pub month: u8,
+    /// From 1 to the length of the month.
+    pub day: u8,
+
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+
+}
+
+impl CalendarDate {
+
+    const SECONDS_PER_DAY: i64 = 60 * 60 * 24;
+    /// Days from 0000-01-01 to 0000-03-01, as year 0 is a leap year.
+    const DAYS_BEFORE_MARCH: i64 = 31 + 29;
+    const DAYS_PER_ERA: i64 = 146_097;
+
+    pub fn new(year: i64, month: u8, day: u8) -> Option<Self> {
+
+        Self::new_with_time(year, month, day, 0, 0, 0)
+    }
+
+    pub fn new_with_time(year: i64, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Option<Self> {
+
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        if hour >= 24 || minute >= 60 || second >= 60 {
+            return None;
+        }
+
+        Some(Self {
+            year,
+            month,
+            day,
+
+            hour,
+            minute,
+            second,
+        })
+    }
+
+
+
+    pub fn from_date(date: GameDate) -> Self {
+        let seconds = date.since_epoch().as_seconds();
+        let days = seconds.div_euclid(Self::SECONDS_PER_DAY);
+        let day_seconds = seconds.rem_euclid(Self::SECONDS_PER_DAY);
+
+        // Years are counted from March, so the leap day ends a year.
+        let march_days = days - Self::DAYS_BEFORE_MARCH;
+        let era = march_days.div_euclid(Self::DAYS_PER_ERA);
+        let day_of_era = march_days.rem_euclid(Self::DAYS_PER_ERA);
+        let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let march_month = (5 * day_of_year + 2) / 153;
+
+        let day = (day_of_year - (153 * march_month + 2) / 5 + 1) as u8;
+        let month = if march_month < 10 { march_month + 3 } else { march_month - 9 } as u8;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year,
+            month,
+            day,
+
+            hour: (day_seconds / 3_600) as u8,
+            minute: (day_seconds / 60 % 60) as u8,
+            second: (day_seconds % 60) as u8,
+        }
+    }
+
+    pub fn to_date(self) -> GameDate {
+        let march_year = if self.month <= 2 { self.year - 1 } else { self.year };
+        let era = march_year.div_euclid(400);
+        let year_of_era = march_year.rem_euclid(400);
+        let march_month = if self.month > 2 { self.month as i64 - 3 } else { self.month as i64 + 9 };
+        let day_of_year = (153 * march_month + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * Self::DAYS_PER_ERA + day_of_era + Self::DAYS_BEFORE_MARCH;
+
+        GameDate::from_since_epoch(TimeSpan::from_seconds(
+            days * Self::SECONDS_PER_DAY
+                + self.hour as i64 * 3_600
+                + self.minute as i64 * 60
+                + self.second as i64
+        ))
+    }
+
+
+
+    /// Day of month is clamped, so 31st of January plus one month is the last day of February.
+    pub fn add_months(self, months: i64) -> Self {
+        let month_index = self.year * 12 + self.month as i64 - 1 + months;
+        let year = month_index.div_euclid(12);
+        let month = month_index.rem_euclid(12) as u8 + 1;
+
+        Self {
+            year,
+            month,
+            day: self.day.min(days_in_month(year, month)),
+            .. self
+        }
+    }
+
+    pub fn add_years(self, years: i64) -> Self {
+
+        self.add_months(years * 12)
+    }
+
+    pub fn has_time_of_day(&self) -> bool {
+
+        self.hour != 0 || self.minute != 0 || self.second != 0
+    }
+
+}
+
+/// Formats as `1918-11-11`, adding ` 05:00:00` only if it isn't midnight.
+impl FmtDisplay for CalendarDate {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+
+        write!(formatter, "{:04}-{:02}-{:02}", self.year, self.month, self.day)?;
+
+        if self.has_time_of_day() {
+            write!(formatter, " {:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses `1918-11-11`, optionally followed by ` 05:00`, or ` 05:00:00`.
+impl FromStr for CalendarDate {
+    type Err = CoreError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid_date = || CoreError::InvalidDate { text: text.to_string() };
+
+        let (date_text, time_text) = match text.trim().split_once(' ') {
+            Some((date_text, time_text)) => (date_text, Some(time_text.trim())),
+            None => (text.trim(), None),
+        };
+
+        // Leading minus belongs to the year, not to the separator.
+        let (year_sign, unsigned_date_text) = match date_text.strip_prefix('-') {
+            Some(unsigned_date_text) => (-1, unsigned_date_text),
+            None => (1, date_text),
+        };
+        let date_parts: Vec<&str> = unsigned_date_text.split('-').collect();
+        if date_parts.len() != 3 {
+            return Err(invalid_date());
+        }
+
+        let year = date_parts[0].parse::<i64>().map_err(|_| invalid_date())? * year_sign;
+        let month = date_parts[1].parse().map_err(|_| invalid_date())?;
+        let day = date_parts[2].parse().map_err(|_| invalid_date())?;
+
+        let (hour, minute, second) = match time_text {
+            Some(time_text) => {
+                let time_parts = time_text.split(':')
+                    .map(|part| part.parse::<u8>())
+                    .collect::<Result<Vec<u8>, _>>()
+                    .map_err(|_| invalid_date())?;
+
+                match time_parts[..] {
+                    [hour, minute] => (hour, minute, 0),
+                    [hour, minute, second] => (hour, minute, second),
+                    _ => return Err(invalid_date()),
+                }
+            },
+            None => (0, 0, 0),
+        };
+
+        Self::new_with_time(year, month, day, hour, minute, second).ok_or_else(invalid_date)
+    }
+}
+
+
+
+pub fn is_leap_year(year: i64) -> bool {
+
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub fn days_in_month(year: i64, month: u8) -> u8 {
+
+    match month {
+        2 => if is_leap_year(year) { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn days_since_epoch(date: CalendarDate) -> i64 {
+
+        date.to_date().since_epoch().as_seconds().div_euclid(CalendarDate::SECONDS_PER_DAY)
+    }
+
+    #[test]
+    fn leap_years() {
+        assert!(is_leap_year(0));
+        assert!(is_leap_year(1916));
+        assert!(is_leap_year(2000));
+        assert!(is_leap_year(-4));
+        assert!(!is_leap_year(1900));
+        assert!(!is_leap_year(1918));
+        assert!(!is_leap_year(-1));
+
+        assert_eq!(days_in_month(1916, 2), 29);
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(1918, 11), 30);
+    }
+
+    #[test]
+    fn epoch_boundaries() {
+        assert_eq!(CalendarDate::new(0, 1, 1).unwrap().to_date(), GameDate::EPOCH);
+        assert_eq!(CalendarDate::from_date(GameDate::EPOCH), CalendarDate::new(0, 1, 1).unwrap());
+
+        let last_second_before = GameDate::from_since_epoch(TimeSpan::from_seconds(-1));
+        assert_eq!(CalendarDate::from_date(last_second_before), CalendarDate::new_with_time(-1, 12, 31, 23, 59, 59).unwrap());
+
+        assert_eq!(days_since_epoch(CalendarDate::new(0, 2, 29).unwrap()), 59);
+        assert_eq!(days_since_epoch(CalendarDate::new(0, 3, 1).unwrap()), 60);
+        assert_eq!(days_since_epoch(CalendarDate::new(1970, 1, 1).unwrap()), 719_528);
+    }
+
+    #[test]
+    fn days_round_trip_across_eras() {
+        let mut previous = CalendarDate::from_date(GameDate::from_since_epoch(TimeSpan::from_days(-800 * 366)));
+
+        for days in (-800 * 366 + 1)..(2_500 * 366) {
+            let date = CalendarDate::from_date(GameDate::from_since_epoch(TimeSpan::from_days(days)));
+            assert_eq!(days_since_epoch(date), days);
+
+            // Every date follows the previous one by a single day.
+            let expected = if previous.day < days_in_month(previous.year, previous.month) {
+                CalendarDate { day: previous.day + 1, .. previous }
+            } else if previous.month < 12 {
+                CalendarDate { month: previous.month + 1, day: 1, .. previous }
+            } else {
+                CalendarDate { year: previous.year + 1, month: 1, day: 1, .. previous }
+            };
+            assert_eq!(date, expected);
+
+            previous = date;
+        }
+    }
+
+    #[test]
+    fn text_round_trip() {
+        for text in ["1918-11-11", "1918-11-11 05:00:00", "0000-01-01", "2000-02-29 23:59:59", "-044-03-15"] {
+            let date: CalendarDate = text.parse().unwrap();
+
+            assert_eq!(date.to_string(), text);
+            assert_eq!(CalendarDate::from_date(date.to_date()), date);
+        }
+
+        assert_eq!("1918-11-11 05:00".parse::<CalendarDate>().unwrap(), CalendarDate::new_with_time(1918, 11, 11, 5, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn invalid_text() {
+        for text in [
+            "1918-02-30", "1900-02-29", "1918-13-01", "1918-00-10", "1918-04-31", "1918-11-00",
+            "1918-11-11 24:00", "1918-11-11 05:60", "1918-11-11 05:00:00:00", "1918-11", "11/11/1918", "",
+        ] {
+            assert!(text.parse::<CalendarDate>().is_err(), "`{text}` was accepted");
+        }
+    }
+
+    #[test]
+    fn months_are_clamped() {
+        let date = CalendarDate::new(1916, 1, 31).unwrap();
+
+        assert_eq!(date.add_months(1), CalendarDate::new(1916, 2, 29).unwrap());
+        assert_eq!(date.add_years(1).add_months(1), CalendarDate::new(1917, 2, 28).unwrap());
+        assert_eq!(date.add_months(-2), CalendarDate::new(1915, 11, 30).unwrap());
+    }
+
+}
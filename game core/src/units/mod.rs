@@ -0,0 +1,270 @@
+use std::fmt::{Debug, Display as FmtDisplay, Formatter};
+use std::ops::{Add, AddAssign, Div, Mul, Rem, Sub, SubAssign};
+use std::str::FromStr;
+use crate::error::CoreError;
+use crate::units::calendar::CalendarDate;
+
+pub mod calendar;
+
+
+
+/// Length of time, counted in seconds.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct TimeSpan ( i64);
+
+impl TimeSpan {
+
+    pub const ZERO: TimeSpan = TimeSpan::from_seconds(0);
+    pub const YEAR: TimeSpan = TimeSpan::from_years(1);
+    pub const HOUR: TimeSpan = TimeSpan::from_hours(1);
+
+    pub const fn from_seconds(seconds: i64) -> Self {
+
+        Self ( seconds)
+    }
+
+    pub const fn from_minutes(minutes: i64) -> Self {
+
+        Self ( minutes * 60)
+    }
+
+    pub const fn from_hours(hours: i64) -> Self {
+
+        Self ( hours * 60 * 60)
+    }
+
+    pub const fn from_days(days: i64) -> Self {
+
+        Self ( days * 60 * 60 * 24)
+    }
+
+    pub const fn from_weeks(weeks: i64) -> Self {
+
+        Self ( weeks * 60 * 60 * 24 * 7)
+    }
+
+    /// Average month length, for calendar months use `GameDate::add_months`.
+    pub const fn from_months(months: i64) -> Self {
+
+        Self ( months * 60 * 60 * 6 * (365 * 4 + 1) / 12)
+    }
+
+    /// Average year length, for calendar years use `GameDate::add_years`.
+    pub const fn from_years(years: i64) -> Self {
+
+        Self ( years * 60 * 60 * 6 * (365 * 4 + 1))
+    }
+
+
+
+    pub const fn as_seconds(self) -> i64 {
+
+        self.0
+    }
+
+}
+
+impl Add<TimeSpan> for TimeSpan {
+    type Output = TimeSpan;
+
+    fn add(self, rhs: TimeSpan) -> Self::Output {
+
+        Self ( self.0 + rhs.0)
+    }
+}
+
+impl AddAssign<TimeSpan> for TimeSpan {
+    fn add_assign(&mut self, rhs: TimeSpan) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub<TimeSpan> for TimeSpan {
+    type Output = TimeSpan;
+
+    fn sub(self, rhs: TimeSpan) -> Self::Output {
+
+        Self ( self.0 - rhs.0)
+    }
+}
+
+impl SubAssign<TimeSpan> for TimeSpan {
+    fn sub_assign(&mut self, rhs: TimeSpan) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Mul<i64> for TimeSpan {
+    type Output = TimeSpan;
+
+    fn mul(self, rhs: i64) -> Self::Output {
+
+        Self ( self.0 * rhs)
+    }
+}
+
+/// Rounds towards negative infinity, like the other divisions.
+impl Div<i64> for TimeSpan {
+    type Output = TimeSpan;
+
+    fn div(self, rhs: i64) -> Self::Output {
+
+        Self ( self.0.div_euclid(rhs))
+    }
+}
+
+/// How many whole periods fit in the span.
+impl Div<TimeSpan> for TimeSpan {
+    type Output = i64;
+
+    fn div(self, rhs: TimeSpan) -> Self::Output {
+
+        self.0.div_euclid(rhs.0)
+    }
+}
+
+impl Rem<TimeSpan> for TimeSpan {
+    type Output = TimeSpan;
+
+    fn rem(self, rhs: TimeSpan) -> Self::Output {
+
+        Self ( self.0.rem_euclid(rhs.0))
+    }
+}
+
+
+
+/// Point in world time, in seconds from midnight of 0000-01-01, see `CalendarDate`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GameDate ( i64);
+
+impl GameDate {
+
+    pub const EPOCH: GameDate = GameDate ( 0);
+
+    pub const fn from_since_epoch(since_epoch: TimeSpan) -> Self {
+
+        Self ( since_epoch.0)
+    }
+
+    pub fn from_calendar(date: CalendarDate) -> Self {
+
+        date.to_date()
+    }
+
+
+
+    pub const fn since_epoch(self) -> TimeSpan {
+
+        TimeSpan ( self.0)
+    }
+
+    pub fn to_calendar(self) -> CalendarDate {
+
+        CalendarDate::from_date(self)
+    }
+
+
+
+    pub fn add_months(self, months: i64) -> Self {
+
+        self.to_calendar().add_months(months).to_date()
+    }
+
+    pub fn add_years(self, years: i64) -> Self {
+
+        self.to_calendar().add_years(years).to_date()
+    }
+
+}
+
+impl Debug for GameDate {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+
+        write!(formatter, "GameDate({})", self.to_calendar())
+    }
+}
+
+impl FmtDisplay for GameDate {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+
+        write!(formatter, "{}", self.to_calendar())
+    }
+}
+
+impl FromStr for GameDate {
+    type Err = CoreError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+
+        Ok(text.parse::<CalendarDate>()?.to_date())
+    }
+}
+
+impl Add<TimeSpan> for GameDate {
+    type Output = GameDate;
+
+    fn add(self, rhs: TimeSpan) -> Self::Output {
+
+        Self ( self.0 + rhs.0)
+    }
+}
+
+impl AddAssign<TimeSpan> for GameDate {
+    fn add_assign(&mut self, rhs: TimeSpan) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub<TimeSpan> for GameDate {
+    type Output = GameDate;
+
+    fn sub(self, rhs: TimeSpan) -> Self::Output {
+
+        Self ( self.0 - rhs.0)
+    }
+}
+
+impl SubAssign<TimeSpan> for GameDate {
+    fn sub_assign(&mut self, rhs: TimeSpan) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Sub<GameDate> for GameDate {
+    type Output = TimeSpan;
+
+    fn sub(self, rhs: GameDate) -> Self::Output {
+
+        TimeSpan ( self.0 - rhs.0)
+    }
+}
+
+/// Time passed since the last period boundary, with periods counted from `GameDate::EPOCH`.
+impl Rem<TimeSpan> for GameDate {
+    type Output = TimeSpan;
+
+    fn rem(self, rhs: TimeSpan) -> Self::Output {
+
+        self.since_epoch() % rhs
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn negative_spans_round_down() {
+        let span = TimeSpan::from_seconds(-7);
+
+        assert_eq!(span / 2, TimeSpan::from_seconds(-4));
+        assert_eq!(span / TimeSpan::from_seconds(2), -4);
+        assert_eq!(span % TimeSpan::from_seconds(2), TimeSpan::from_seconds(1));
+        assert_eq!(TimeSpan::from_seconds(7) / 2, TimeSpan::from_seconds(3));
+    }
+
+}
@@ -65,6 +65,8 @@ pub struct Camera {
     pub min_position: [f32; 3],
     pub max_position: [f32; 3],
     pub limit_z_dependency: f32,
+    /// Map width, for maps wrapping around on the x axis, where the camera isn't bound on it.
+    pub wrap_width: Option<f32>,
 
     pub height_move_step: f32,
 
@@ -76,8 +78,8 @@ pub struct Camera {
 
 impl Camera {
 
-    pub fn new(max_map_axis: (u32, u32)) -> Self {
-        let max_map_axis = (max_map_axis.0 as f32 / 2.0, max_map_axis.1 as f32 / 2.0);
+    pub fn new(map_display_size: (f32, f32)) -> Self {
+        let max_map_axis = (map_display_size.0 / 2.0, map_display_size.1 / 2.0);
 
         Self {
             rotation: RotationXYZ::ZERO,
@@ -89,6 +91,7 @@ impl Camera {
             min_position: [-max_map_axis.0, -max_map_axis.1, -75.0],
             max_position: [max_map_axis.0, max_map_axis.1, -2.0],
             limit_z_dependency: 0.475,
+            wrap_width: None,
 
             height_move_step: 0.175,
 
@@ -109,12 +112,14 @@ impl Camera {
         let mut motion_rate = self.movement_softness * effect_rate;
         if motion_rate > 1.0 { motion_rate = 1.0; }
 
-        Self::motion_between_bounds(
-            self.position[0],
-            &mut self.position_add_target[0],
-            self.min_position[0] + self.position[2] * self.limit_z_dependency,
-            self.max_position[0] - self.position[2] * self.limit_z_dependency
-        );
+        if self.wrap_width.is_none() {
+            Self::motion_between_bounds(
+                self.position[0],
+                &mut self.position_add_target[0],
+                self.min_position[0] + self.position[2] * self.limit_z_dependency,
+                self.max_position[0] - self.position[2] * self.limit_z_dependency
+            );
+        }
         Self::motion_between_bounds(
             self.position[1],
             &mut self.position_add_target[1],
@@ -140,6 +145,10 @@ impl Camera {
         self.position_add_target[1] -= motion_y;
         self.position_add_target[2] -= motion_z;
 
+        if let Some(wrap_width) = self.wrap_width {
+            self.position[0] = (self.position[0] + wrap_width / 2.0).rem_euclid(wrap_width) - wrap_width / 2.0;
+        }
+
 
 
         let x_rotation_full_scale = (self.max_position[2] - self.min_position[2]).powi(self.x_rotation_scale_power);
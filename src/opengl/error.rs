@@ -1,7 +1,9 @@
 use std::fmt::{Debug, Display as FmtDisplay, Formatter};
 use glium::{DrawError, ProgramCreationError, SwapBuffersError};
 use glium::glutin::CreationError;
+use glium::texture::TextureCreationError;
 use glium::vertex::BufferCreationError as VertexBufferCreationError;
+use war_economy_core::error::{CoreError, CoreResult};
 
 
 
@@ -41,6 +43,16 @@ impl<T> ToInterfaceError<T> for Result<T, VertexBufferCreationError> {
     }
 }
 
+impl<T> ToInterfaceError<T> for Result<T, TextureCreationError> {
+    fn to_interface_error(self) -> Result<T, InterfaceError> {
+
+        match self {
+            Ok(t) => Ok(t),
+            Err(error) => Err(InterfaceError::GliumCreation(GliumCreationError::Texture(error))),
+        }
+    }
+}
+
 impl<T> ToInterfaceError<T> for Result<T, DrawError> {
     fn to_interface_error(self) -> Result<T, InterfaceError> {
 
@@ -51,6 +63,16 @@ impl<T> ToInterfaceError<T> for Result<T, DrawError> {
     }
 }
 
+impl<T> ToInterfaceError<T> for CoreResult<T> {
+    fn to_interface_error(self) -> Result<T, InterfaceError> {
+
+        match self {
+            Ok(t) => Ok(t),
+            Err(error) => Err(InterfaceError::Core(error)),
+        }
+    }
+}
+
 impl<T> ToInterfaceError<T> for Result<T, SwapBuffersError> {
     fn to_interface_error(self) -> Result<T, InterfaceError> {
 
@@ -69,6 +91,7 @@ pub enum GliumCreationError {
     Default(CreationError),
     Program(ProgramCreationError),
     VertexBuffer(VertexBufferCreationError),
+    Texture(TextureCreationError),
 
 }
 
@@ -79,6 +102,7 @@ pub enum InterfaceError {
     GliumCreation(GliumCreationError),
     Draw(DrawError),
     BufferSwapping(SwapBuffersError),
+    Core(CoreError),
     Other(String),
 
 }
@@ -99,9 +123,11 @@ impl FmtDisplay for InterfaceError {
                 GliumCreationError::Default(error) => output_error("data structure creation error > ", formatter, error),
                 GliumCreationError::Program(error) => output_error("compilation failed > ", formatter, error),
                 GliumCreationError::VertexBuffer(error) => output_error("sending of vertex buffer failed > ", formatter, error),
+                GliumCreationError::Texture(error) => output_error("texture creation failed > ", formatter, error),
             },
             InterfaceError::Draw(error) => output_error("draw error > ", formatter, error),
             InterfaceError::BufferSwapping(error) => output_error("buffer swapping error > ", formatter, error),
+            InterfaceError::Core(error) => output_error("game core error > ", formatter, error),
             InterfaceError::Other(error) => formatter.write_str(error),
         }
     }
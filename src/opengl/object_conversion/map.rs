@@ -1,77 +1,133 @@
 use war_economy_core::map::{MapSettings, MapShape};
-use war_economy_core::map::tile::TileArray;
+use war_economy_core::map::coordinates::{Direction, HexDirection};
+use war_economy_core::map::tile::chunks::{TileChunk, TileChunks};
 use crate::opengl::triangles::MapVertex;
+use crate::units::Angle;
 
-pub fn map_tiles_to_vertexes(terrain: (&MapSettings, &TileArray)) -> Vec<MapVertex> {
+/// Tiles of a single chunk, placed on the whole map, with texture coordinates into the chunk's own texture.
+pub fn chunk_tiles_to_vertexes(terrain: (&MapSettings, &TileChunks), chunk: &TileChunk) -> Vec<MapVertex> {
     let (map_settings, tiles) = terrain;
+    let first_tile = chunk.coordinates().first_tile();
+    let (chunk_width, chunk_height) = chunk.size();
+    let x_uv_scale = 1.0 / chunk_width as f32;
+    let y_uv_scale = 1.0 / chunk_height as f32;
 
-    let mut vertex_groups = vec![];
+    let mut vertexes = vec![];
 
     match map_settings.shape {
-        MapShape::Rectangular { width, height } => {
-            //let height_usize = height as usize;
+        MapShape::Rectangular { width, height }
+        | MapShape::Cylindrical { width, height } => {
             let x_offset = width as f32 / -2.0;
             let y_offset = height as f32 / -2.0;
 
-            let x_uv_scale = 1.0 / width as f32;
-            let y_uv_scale = 1.0 / height as f32;
-
-
-            for (tile_index_usize, central_tile) in tiles.into_iter().enumerate() {
-                let tile_index = tile_index_usize as u32;
-                let tile_y = tile_index / width;
-                let tile_x = tile_index % width;
-
-                let tile_display_y = tile_y as f32 + y_offset;
-                let tile_display_x = tile_x as f32 + x_offset;
-
-                let west_tile = if tile_index == 0 {central_tile} else {tiles.index(tile_index - 1)};
-                let east_tile = tiles.get(tile_index + 1).unwrap_or(central_tile);
-                let south_tile = if tile_index <= width {central_tile} else {tiles.index(tile_index - width)};
-                let north_tile = tiles.get(tile_index + width).unwrap_or(central_tile);
 
-                let south_west_tile = if tile_index < width + 1 {central_tile} else {tiles.index(tile_index - width - 1)};
-                let south_east_tile = if tile_index <= width {central_tile} else {tiles.index(tile_index - width + 1)};
+            for (tile_coordinates, central_tile) in chunk.tiles() {
+                let tile_display_y = tile_coordinates.y as f32 + y_offset;
+                let tile_display_x = tile_coordinates.x as f32 + x_offset;
 
-                let north_west_tile = tiles.get(tile_index + width - 1).unwrap_or(central_tile);
-                let north_east_tile = tiles.get(tile_index + width + 1).unwrap_or(central_tile);
+                // Past the map edge the central tile stands in, cylindrical maps wrap around instead.
+                let neighbour_height = |direction: Direction| {
+                    tiles.neighbour(tile_coordinates, direction)
+                        .unwrap_or(central_tile)
+                        .main.height.to_f32_rescaled(map_settings.tile_size)
+                };
 
 
-                vertex_groups.push(create_tile_square(
-                    x_uv_scale,
-                    y_uv_scale,
+                vertexes.extend_from_slice(&create_tile_square(
+                    (x_uv_scale, y_uv_scale),
+                    ((tile_coordinates.x - first_tile.x) as f32, (tile_coordinates.y - first_tile.y) as f32),
                     tile_display_x,
                     tile_display_y,
                     central_tile.main.height.to_f32_rescaled(map_settings.tile_size),
-                    west_tile.main.height.to_f32_rescaled(map_settings.tile_size),
-                    east_tile.main.height.to_f32_rescaled(map_settings.tile_size),
-                    north_tile.main.height.to_f32_rescaled(map_settings.tile_size),
-                    south_tile.main.height.to_f32_rescaled(map_settings.tile_size),
-                    south_west_tile.main.height.to_f32_rescaled(map_settings.tile_size),
-                    south_east_tile.main.height.to_f32_rescaled(map_settings.tile_size),
-                    north_west_tile.main.height.to_f32_rescaled(map_settings.tile_size),
-                    north_east_tile.main.height.to_f32_rescaled(map_settings.tile_size),
+                    neighbour_height(Direction::West),
+                    neighbour_height(Direction::East),
+                    neighbour_height(Direction::North),
+                    neighbour_height(Direction::South),
+                    neighbour_height(Direction::SouthWest),
+                    neighbour_height(Direction::SouthEast),
+                    neighbour_height(Direction::NorthWest),
+                    neighbour_height(Direction::NorthEast),
                 ));
 
             }
 
         },
-    }
+        MapShape::Hexagonal { .. } => {
+            let (display_width, display_height) = map_settings.shape.display_size();
+            let x_offset = display_width / -2.0;
+            let y_offset = display_height / -2.0;
 
-    let mut vertexes = vec![];
 
-    for vertex_group in vertex_groups {
-        for vertex in vertex_group {
-            vertexes.push(vertex);
-        }
+            for (tile_coordinates, central_tile) in chunk.tiles() {
+                let (center_x, center_y) = map_settings.shape.tile_center(tile_coordinates);
+
+                vertexes.extend_from_slice(&create_tile_hexagon(
+                    (x_uv_scale, y_uv_scale),
+                    (tile_coordinates.x - first_tile.x) as f32,
+                    (tile_coordinates.y - first_tile.y) as f32,
+                    center_x + x_offset,
+                    center_y + y_offset,
+                    central_tile.main.height.to_f32_rescaled(map_settings.tile_size),
+                    HexDirection::ALL.map(|direction| {
+                        tiles.hex_neighbour(tile_coordinates, direction)
+                            .unwrap_or(central_tile)
+                            .main.height.to_f32_rescaled(map_settings.tile_size)
+                    }),
+                ));
+            }
+
+        },
     }
 
     vertexes
 }
 
+/// `neighbour_heights` follow `HexDirection::ALL`, so corner `n` lies between neighbours `n` and `n + 1`.
+/// `tile_x` and `tile_y` are counted from the chunk's first tile, as they only place the tile on the chunk's texture.
+fn create_tile_hexagon(
+    (x_uv_scale, y_uv_scale): (f32, f32),
+    tile_x: f32,
+    tile_y: f32,
+    center_x: f32,
+    center_y: f32,
+    central_height: f32,
+    neighbour_heights: [f32; 6],
+) -> [MapVertex; 18] {
+    let central_x_uv = (tile_x + 0.5) * x_uv_scale;
+    let central_y_uv = (tile_y + 0.5) * y_uv_scale;
+    let corner_radius = 1.0 / 3.0_f32.sqrt();
+
+    let corners: [MapVertex; 6] = std::array::from_fn(|corner_index| {
+        let angle = Angle::from_degrees(30.0 + 60.0 * corner_index as f32);
+        let corner_x = angle.cos() * corner_radius;
+        let corner_y = angle.sin() * corner_radius;
+        let corner_height = (central_height + neighbour_heights[corner_index] + neighbour_heights[(corner_index + 1) % 6]) / 3.0;
+
+        MapVertex {
+            position: [center_x + corner_x, center_y + corner_y, -corner_height],
+            // Hexagon is inscribed in the tile's square of the texture.
+            surface_uv: [
+                central_x_uv + corner_x * x_uv_scale,
+                central_y_uv + corner_y * MapShape::HEXAGON_ROW_HEIGHT * y_uv_scale,
+            ],
+        }
+    });
+    let center = MapVertex {
+        position: [center_x, center_y, -central_height],
+        surface_uv: [central_x_uv, central_y_uv],
+    };
+
+    std::array::from_fn(|vertex_index| match vertex_index % 3 {
+        0 => center,
+        1 => corners[vertex_index / 3],
+        _ => corners[(vertex_index / 3 + 1) % 6],
+    })
+}
+
+/// `tile_x` and `tile_y` place the tile on the map, `uv_tile_x` and `uv_tile_y` on the chunk's texture.
 fn create_tile_square(
-    x_uv_scale: f32,
-    y_uv_scale: f32,
+    (x_uv_scale, y_uv_scale): (f32, f32),
+    (uv_tile_x, uv_tile_y): (f32, f32),
     tile_x: f32,
     tile_y: f32,
     central_height: f32,
@@ -84,13 +140,13 @@ fn create_tile_square(
     north_west_height: f32,
     north_east_height: f32,
 ) -> [MapVertex; 24] {
-    let neg_x_uv = tile_x * x_uv_scale + 0.5;
-    let central_x_uv = (tile_x + 0.5) * x_uv_scale + 0.5;
-    let pos_x_uv = (tile_x + 1.0) * x_uv_scale + 0.5;
+    let neg_x_uv = uv_tile_x * x_uv_scale;
+    let central_x_uv = (uv_tile_x + 0.5) * x_uv_scale;
+    let pos_x_uv = (uv_tile_x + 1.0) * x_uv_scale;
 
-    let neg_y_uv = tile_y * y_uv_scale + 0.5;
-    let central_y_uv = (tile_y + 0.5) * y_uv_scale + 0.5;
-    let pos_y_uv = (tile_y + 1.0) * y_uv_scale + 0.5;
+    let neg_y_uv = uv_tile_y * y_uv_scale;
+    let central_y_uv = (uv_tile_y + 0.5) * y_uv_scale;
+    let pos_y_uv = (uv_tile_y + 1.0) * y_uv_scale;
 
     let west_central_height = (west_height + central_height) / 2.0;
     let east_central_height = (east_height + central_height) / 2.0;
@@ -2,10 +2,13 @@ use std::time::Duration;
 use glium::{BackfaceCullingMode, Depth, DepthTest, Display, DrawParameters, Program, Surface, VertexBuffer};
 use glium::texture::{RawImage2d, SrgbTexture2d};
 use glium::uniforms::{MagnifySamplerFilter, Sampler};
-use winit::event::{KeyboardInput, MouseScrollDelta, TouchPhase};
+use winit::event::{ElementState, KeyboardInput, MouseScrollDelta, TouchPhase, VirtualKeyCode};
 use war_economy_core::Game;
+use war_economy_core::error::CoreError;
+use war_economy_core::image::ImageDimensions;
+use war_economy_core::map::tile::chunks::{ChunkCoordinates, CHUNK_SIZE};
 use crate::opengl::algorithms::{Camera, KeyControls};
-use crate::opengl::object_conversion::map::map_tiles_to_vertexes;
+use crate::opengl::object_conversion::map::chunk_tiles_to_vertexes;
 use crate::opengl::panels::{Panel};
 use crate::opengl::triangles::MapVertex;
 use crate::opengl::error::{InterfaceError, ToInterfaceError};
@@ -14,8 +17,13 @@ use crate::units::{Angle, Matrix4x4};
 pub struct GamePanel {
 
     terrain_program: Program,
-    map_vertex_buffer: VertexBuffer<MapVertex>,
-    map_texture: SrgbTexture2d,
+    map_chunks: Vec<MapChunk>,
+    political_texture: SrgbTexture2d,
+    /// Border revision the political texture was drawn for.
+    political_revision: u64,
+    show_political: bool,
+    /// Last one given to the window, so it's only set again once the date or speed changes.
+    title: String,
 
     keyboard: KeyControls,
     camera: Camera,
@@ -27,19 +35,26 @@ pub struct GamePanel {
 impl GamePanel {
 
     pub fn new(display: &Display) -> Result<Self, InterfaceError> {
-        let game = Game::new();
-        let mut camera = Camera::new(game.map.properties.shape.max_axis());
+        let game = Game::new().to_interface_error()?;
+        let map_shape = game.map.properties.shape;
+        let mut camera = Camera::new(map_shape.display_size());
+        if map_shape.wraps_x() {
+            camera.wrap_width = Some(map_shape.display_size().0);
+        }
         camera.position = [0.0, -5.0, -12.5];
         camera.rotation.x = Angle::from_degrees(5.0);
 
-        let raw_map_image = game.definitions.surface_types.build_surface_texture(&game.map).unwrap();
-        let map_image = RawImage2d::from_raw_rgb(raw_map_image.raw_u8_bytes(), raw_map_image.dimensions().to_u32_tuple());
-        let map_texture = SrgbTexture2d::new(display, map_image).unwrap();
+        let map_chunks = game.map.get_terrain().1.chunks().iter()
+            .map(|chunk| MapChunk::new(display, &game, chunk.coordinates()))
+            .collect::<Result<Vec<MapChunk>, InterfaceError>>()?;
 
         Ok(Self {
             terrain_program: Program::from_source(display, VERTEX_SHADER, FRAGMENT_SHADER, None).to_interface_error()?,
-            map_vertex_buffer: VertexBuffer::new(display, &map_tiles_to_vertexes(game.map.get_terrain())).to_interface_error()?,
-            map_texture,
+            map_chunks,
+            political_texture: Self::build_political_texture(display, &game)?,
+            political_revision: game.map.borders().revision(),
+            show_political: false,
+            title: String::new(),
 
             keyboard: KeyControls::new(),
             camera,
@@ -48,6 +63,96 @@ impl GamePanel {
         })
     }
 
+    fn build_political_texture(display: &Display, game: &Game) -> Result<SrgbTexture2d, InterfaceError> {
+        let raw_political_image = game.map.political_map_image(&game.powers, ImageDimensions::new(32, 32)).to_interface_error()?;
+        let political_image = RawImage2d::from_raw_rgb(raw_political_image.raw_u8_bytes(), raw_political_image.dimensions().to_u32_tuple());
+
+        SrgbTexture2d::new(display, political_image).to_interface_error()
+    }
+
+    /// Day of the world time, without the hour, and the clock speed.
+    fn window_title(&self) -> String {
+        let date = self.game.world_time().to_calendar();
+        let speed = if self.game.clock.is_paused() {
+            "paused".to_string()
+        } else {
+            format!("speed {}", self.game.clock.speed_multiplier())
+        };
+
+        format!("Wojenka - {:04}-{:02}-{:02} ({speed})", date.year, date.month, date.day)
+    }
+
+    /// Rebuilds chunks, that changed since the last call, leaving others as they are.
+    /// Heights and transitions reach over chunk edges, so chunks next to changed ones are rebuilt too.
+    fn rebuild_dirty_chunks(&mut self, display: &Display) -> Result<(), InterfaceError> {
+        let map_shape = self.game.map.properties.shape;
+        let chunk_columns = map_shape.max_axis().0.div_ceil(CHUNK_SIZE) as i64;
+
+        let mut changed_chunks = vec![];
+        for dirty_chunk in self.game.map.take_dirty_chunks() {
+            for (x_shift, y_shift) in (-1..=1).flat_map(|x_shift| (-1..=1).map(move |y_shift| (x_shift, y_shift))) {
+                let mut chunk_x = dirty_chunk.x as i64 + x_shift;
+                if map_shape.wraps_x() {
+                    chunk_x = chunk_x.rem_euclid(chunk_columns);
+                }
+                let chunk_y = dirty_chunk.y as i64 + y_shift;
+
+                if chunk_x >= 0 && chunk_y >= 0 {
+                    changed_chunks.push(ChunkCoordinates::new(chunk_x as u32, chunk_y as u32));
+                }
+            }
+        }
+        changed_chunks.sort_unstable();
+        changed_chunks.dedup();
+
+        for map_chunk in &mut self.map_chunks {
+            if changed_chunks.binary_search(&map_chunk.coordinates).is_ok() {
+                *map_chunk = MapChunk::new(display, &self.game, map_chunk.coordinates)?;
+            }
+        }
+
+        Ok(())
+    }
+
+}
+
+
+
+/// Part of the map with its own vertexes and surface texture, so it can be rebuilt alone.
+struct MapChunk {
+
+    coordinates: ChunkCoordinates,
+    vertex_buffer: VertexBuffer<MapVertex>,
+    surface_texture: SrgbTexture2d,
+    /// Turn texture coordinates of the chunk's texture into ones of whole map textures, like the political one.
+    map_uv_scale: [f32; 2],
+    map_uv_offset: [f32; 2],
+
+}
+
+impl MapChunk {
+
+    fn new(display: &Display, game: &Game, coordinates: ChunkCoordinates) -> Result<Self, InterfaceError> {
+        let terrain = game.map.get_terrain();
+        let chunk = terrain.1.chunk(coordinates)
+            .ok_or_else(|| InterfaceError::Core(CoreError::InvalidChunk { problem: format!("{coordinates} is outside of the map") }))?;
+
+        let raw_surface_image = game.definitions.surface_types.build_chunk_surface_texture(&game.map, coordinates).to_interface_error()?;
+        let surface_image = RawImage2d::from_raw_rgb(raw_surface_image.raw_u8_bytes(), raw_surface_image.dimensions().to_u32_tuple());
+
+        let (map_width, map_height) = game.map.properties.shape.max_axis();
+        let (chunk_width, chunk_height) = chunk.size();
+        let first_tile = coordinates.first_tile();
+
+        Ok(Self {
+            coordinates,
+            vertex_buffer: VertexBuffer::new(display, &chunk_tiles_to_vertexes(terrain, chunk)).to_interface_error()?,
+            surface_texture: SrgbTexture2d::new(display, surface_image).to_interface_error()?,
+            map_uv_scale: [chunk_width as f32 / map_width as f32, chunk_height as f32 / map_height as f32],
+            map_uv_offset: [first_tile.x as f32 / map_width as f32, first_tile.y as f32 / map_height as f32],
+        })
+    }
+
 }
 
 impl Panel for GamePanel {
@@ -55,6 +160,16 @@ impl Panel for GamePanel {
 
         self.keyboard.process_input(keyboard_input, is_synthetic);
 
+        if !is_synthetic && keyboard_input.state == ElementState::Pressed {
+            match keyboard_input.virtual_keycode {
+                Some(VirtualKeyCode::Space) => self.game.clock.toggle_pause(),
+                Some(VirtualKeyCode::Tab) => self.show_political = !self.show_political,
+                Some(VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd) => self.game.clock.speed_up(),
+                Some(VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract) => self.game.clock.slow_down(),
+                _ => {},
+            }
+        }
+
         Ok(())
     }
 
@@ -69,6 +184,18 @@ impl Panel for GamePanel {
     fn redraw(&mut self, display: &Display, last_frame_duration: Duration) -> Result<(), InterfaceError> {
         self.camera.tick(last_frame_duration, &self.keyboard);
 
+        self.game.advance(last_frame_duration);
+        let title = self.window_title();
+        if title != self.title {
+            display.gl_window().window().set_title(&title);
+            self.title = title;
+        }
+        self.rebuild_dirty_chunks(display)?;
+        if self.game.map.borders().revision() != self.political_revision {
+            self.political_texture = Self::build_political_texture(display, &self.game)?;
+            self.political_revision = self.game.map.borders().revision();
+        }
+
         let mut target = display.draw();
 
         let projection_matrix = Matrix4x4::projection_matrix(
@@ -92,19 +219,43 @@ impl Panel for GamePanel {
             .. Default::default()
         };
 
-        target.draw(
-            &self.map_vertex_buffer,
-            &indices,
-            &self.terrain_program,
-            &uniform!(
-                projection: projection_matrix.to_arrays(),
-                rotation: self.camera.rotation.rotation_matrix().to_arrays(),
-                camera_position: self.camera.position,
+        // Wrapping maps are drawn again on both sides, as many times as the camera height can show.
+        let map_copies = match self.camera.wrap_width {
+            Some(wrap_width) => (-self.camera.position[2] * 2.0 / wrap_width).ceil() as i32 + 1,
+            None => 0,
+        };
 
-                map_texture: Sampler::new(&self.map_texture).magnify_filter(MagnifySamplerFilter::Nearest),
-            ),
-            &draw_parameters,
-        ).to_interface_error()?;
+        let projection = projection_matrix.to_arrays();
+        let rotation = self.camera.rotation.rotation_matrix().to_arrays();
+
+        for map_copy in -map_copies..=map_copies {
+            let map_x_shift = map_copy as f32 * self.camera.wrap_width.unwrap_or(0.0);
+
+            for map_chunk in &self.map_chunks {
+                let (map_texture, uv_scale, uv_offset) = if self.show_political {
+                    (&self.political_texture, map_chunk.map_uv_scale, map_chunk.map_uv_offset)
+                } else {
+                    (&map_chunk.surface_texture, [1.0, 1.0], [0.0, 0.0])
+                };
+
+                target.draw(
+                    &map_chunk.vertex_buffer,
+                    &indices,
+                    &self.terrain_program,
+                    &uniform!(
+                        projection: projection,
+                        rotation: rotation,
+                        camera_position: self.camera.position,
+                        map_x_shift: map_x_shift,
+                        uv_scale: uv_scale,
+                        uv_offset: uv_offset,
+
+                        map_texture: Sampler::new(map_texture).magnify_filter(MagnifySamplerFilter::Nearest),
+                    ),
+                    &draw_parameters,
+                ).to_interface_error()?;
+            }
+        }
 
         target.finish().to_interface_error()
     }
@@ -120,6 +271,9 @@ in vec2 surface_uv;
 uniform mat4 projection;
 uniform mat3 rotation;
 uniform vec3 camera_position;
+uniform float map_x_shift;
+uniform vec2 uv_scale;
+uniform vec2 uv_offset;
 
 out vec2 v_surface_uv;
 out float v_sun_light;
@@ -128,9 +282,9 @@ void main() {
 
     v_sun_light = position.z / camera_position.z + 0.5;
 
-    gl_Position = projection * vec4(rotation * (position - camera_position), 1.0);
+    gl_Position = projection * vec4(rotation * (position + vec3(map_x_shift, 0.0, 0.0) - camera_position), 1.0);
 
-    v_surface_uv = surface_uv;
+    v_surface_uv = surface_uv * uv_scale + uv_offset;
 
 }
 